@@ -0,0 +1,295 @@
+use crate::Error;
+use decimal::d128;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The exchange rates used for currency conversion, set with
+/// [`set_exchange_rates`]
+static EXCHANGE_RATES: RwLock<Option<ExchangeRates>> = RwLock::new(None);
+
+/// Held by tests that set or depend on [`EXCHANGE_RATES`], since they're
+/// shared by all tests running in parallel
+#[cfg(test)]
+pub(crate) static EXCHANGE_RATES_TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// The codes of the currencies, precious metals and funds in ISO 4217, in
+/// alphabetical order
+const ISO_4217_CODES: [&str; 176] = [
+	"AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+	"BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BOV", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD",
+	"CAD", "CDF", "CHE", "CHF", "CHW", "CLF", "CLP", "CNY", "COP", "COU", "CRC", "CUC", "CUP",
+	"CVE", "CZK", "DJF", "DKK", "DOP", "DZD", "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP",
+	"GEL", "GHS", "GIP", "GMD", "GNF", "GTQ", "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS",
+	"INR", "IQD", "IRR", "ISK", "JMD", "JOD", "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW",
+	"KWD", "KYD", "KZT", "LAK", "LBP", "LKR", "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD",
+	"MMK", "MNT", "MOP", "MRU", "MUR", "MVR", "MWK", "MXN", "MXV", "MYR", "MZN", "NAD", "NGN",
+	"NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN", "PGK", "PHP", "PKR", "PLN", "PYG", "QAR",
+	"RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR", "SDG", "SEK", "SGD", "SHP", "SLE", "SLL",
+	"SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB", "TJS", "TMT", "TND", "TOP", "TRY",
+	"TTD", "TWD", "TZS", "UAH", "UGX", "USD", "USN", "UYI", "UYU", "UYW", "UZS", "VED", "VES",
+	"VND", "VUV", "WST", "XAF", "XAG", "XAU", "XCD", "XCG", "XDR", "XOF", "XPD", "XPF", "XPT",
+	"XSU", "XUA", "YER", "ZAR", "ZMW", "ZWG", "ZWL",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+/// A currency, identified by its ISO 4217 code, like `USD`
+pub struct Currency {
+	/// The code in uppercase ASCII letters
+	code: [u8; 3],
+}
+
+impl Currency {
+	/// Creates a [`Currency`] from an ISO 4217 code like `usd` or `EUR`.
+	/// Returns `None` if the code isn't 3 ASCII letters.
+	pub fn new(code: &str) -> Option<Currency> {
+		let bytes: [u8; 3] = code.as_bytes().try_into().ok()?;
+		if !bytes.iter().all(u8::is_ascii_alphabetic) {
+			return None;
+		}
+		Some(Currency {
+			code: bytes.map(|b| b.to_ascii_uppercase()),
+		})
+	}
+
+	/// The ISO 4217 code of this currency, like `USD`
+	pub fn code(&self) -> &str {
+		std::str::from_utf8(&self.code).unwrap()
+	}
+
+	/// Returns true if the code of this currency is in ISO 4217, and not just
+	/// any 3 letters like `ABC`
+	pub fn is_iso_4217(&self) -> bool {
+		ISO_4217_CODES.binary_search(&self.code()).is_ok()
+	}
+
+	/// How much 1 of the base currency of the current [`ExchangeRates`] is
+	/// worth in this currency, or `None` if there's no rate for it
+	pub fn rate(&self) -> Option<d128> {
+		let rates = EXCHANGE_RATES.read().unwrap_or_else(|e| e.into_inner());
+		rates.as_ref()?.rate(*self)
+	}
+
+	/// The value of 1 of this currency in the base currency of the current
+	/// [`ExchangeRates`], or `None` if there's no rate for it
+	pub fn weight(&self) -> Option<d128> {
+		Some(d128!(1) / self.rate()?)
+	}
+}
+
+impl Display for Currency {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.code())
+	}
+}
+
+impl Debug for Currency {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		Display::fmt(self, f)
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Exchange rates relative to a base currency.
+///
+/// Rates are read from JSON in this format, where `timestamp` is when the
+/// rates were published, in seconds since the Unix epoch:
+///
+/// ```json
+/// {
+///     "base": "USD",
+///     "timestamp": 1760745600,
+///     "rates": { "EUR": 0.86, "GBP": 0.75 }
+/// }
+/// ```
+///
+/// Rates can be numbers or strings, and `time_last_updated` can be used
+/// instead of `timestamp`, so responses from
+/// <https://api.exchangerate-api.com/v4/latest/USD> can be used as is.
+///
+/// Example:
+/// ```rust
+/// use cpc::{eval, set_exchange_rates, ExchangeRates};
+/// use cpc::units::Unit;
+///
+/// let json = r#"{ "base": "USD", "timestamp": 1760745600, "rates": { "EUR": 0.8 } }"#;
+/// set_exchange_rates(ExchangeRates::from_json(json).unwrap());
+/// let answer = eval("$10 to eur", true, Unit::Celsius, false).unwrap();
+/// assert_eq!(answer.to_string(), "8.0 EUR");
+/// ```
+pub struct ExchangeRates {
+	/// The currency the rates are relative to
+	pub base: Currency,
+	/// When the rates were published, in seconds since the Unix epoch
+	pub timestamp: u64,
+	/// How much 1 of the base currency is worth in each currency
+	pub rates: HashMap<Currency, d128>,
+}
+
+impl ExchangeRates {
+	/// Reads [`ExchangeRates`] from JSON
+	pub fn from_json(json: &str) -> Result<ExchangeRates, Error> {
+		let invalid = |reason: &str| Error::InvalidExchangeRates {
+			reason: reason.to_string(),
+		};
+		let json: serde_json::Value =
+			serde_json::from_str(json).map_err(|e| invalid(&e.to_string()))?;
+		let base = json["base"]
+			.as_str()
+			.and_then(Currency::new)
+			.ok_or_else(|| invalid("`base` needs to be a currency code"))?;
+		let timestamp = json
+			.get("timestamp")
+			.or_else(|| json.get("time_last_updated"))
+			.and_then(|timestamp| timestamp.as_u64())
+			.ok_or_else(|| invalid("`timestamp` needs to be a Unix timestamp"))?;
+		let entries = json["rates"]
+			.as_object()
+			.ok_or_else(|| invalid("`rates` needs to be an object"))?;
+		let mut rates = HashMap::new();
+		for (code, rate) in entries {
+			let currency = Currency::new(code)
+				.ok_or_else(|| invalid(&format!("{} is not a currency code", code)))?;
+			let rate = match rate {
+				serde_json::Value::Number(rate) => d128::from_str(&rate.to_string()).ok(),
+				serde_json::Value::String(rate) => d128::from_str(rate).ok(),
+				_ => None,
+			};
+			match rate {
+				Some(rate) if rate.is_positive() && rate.is_finite() => {
+					rates.insert(currency, rate);
+				}
+				_ => {
+					return Err(invalid(&format!(
+						"the rate of {} needs to be a positive number",
+						code
+					)))
+				}
+			}
+		}
+		Ok(ExchangeRates {
+			base,
+			timestamp,
+			rates,
+		})
+	}
+
+	/// Reads [`ExchangeRates`] from a JSON file
+	pub fn load(path: impl AsRef<Path>) -> Result<ExchangeRates, Error> {
+		let path = path.as_ref();
+		let json = fs::read_to_string(path).map_err(|e| Error::InvalidExchangeRates {
+			reason: format!("{}: {}", path.display(), e),
+		})?;
+		ExchangeRates::from_json(&json)
+	}
+
+	/// How much 1 of the base currency is worth in `currency`
+	pub fn rate(&self, currency: Currency) -> Option<d128> {
+		if currency == self.base {
+			return Some(d128!(1));
+		}
+		self.rates.get(&currency).copied()
+	}
+
+	/// How long ago the rates were published
+	pub fn age(&self) -> Duration {
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default();
+		now.saturating_sub(Duration::from_secs(self.timestamp))
+	}
+}
+
+/// Sets the [`ExchangeRates`] used to convert between currencies, replacing
+/// any previous ones. Without exchange rates, currencies can only be
+/// combined with the same currency.
+pub fn set_exchange_rates(rates: ExchangeRates) {
+	*EXCHANGE_RATES.write().unwrap_or_else(|e| e.into_inner()) = Some(rates);
+}
+
+/// Removes the [`ExchangeRates`] set with [`set_exchange_rates`]
+pub fn clear_exchange_rates() {
+	*EXCHANGE_RATES.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Returns the current [`ExchangeRates`], if any are set
+pub fn exchange_rates() -> Option<ExchangeRates> {
+	EXCHANGE_RATES
+		.read()
+		.unwrap_or_else(|e| e.into_inner())
+		.clone()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::eval;
+	use crate::units::Unit;
+
+	#[test]
+	fn test_exchange_rates() {
+		let rates = ExchangeRates::from_json(include_str!("../tests/fixtures/rates.json")).unwrap();
+		assert_eq!(rates.base, Currency::new("usd").unwrap());
+		assert_eq!(rates.timestamp, 1760745600);
+		assert_eq!(rates.rate(Currency::new("EUR").unwrap()), Some(d128!(0.8)));
+		assert_eq!(rates.rate(Currency::new("USD").unwrap()), Some(d128!(1)));
+		assert_eq!(rates.rate(Currency::new("XYZ").unwrap()), None);
+
+		let invalid = |json| ExchangeRates::from_json(json).is_err();
+		assert!(invalid(r#"{ "base": "USD", "rates": {} }"#));
+		assert!(invalid(
+			r#"{ "base": "dollar", "timestamp": 0, "rates": {} }"#
+		));
+		assert!(invalid(
+			r#"{ "base": "USD", "timestamp": 0, "rates": { "EUR": -1 } }"#
+		));
+
+		let _lock = EXCHANGE_RATES_TEST_LOCK
+			.lock()
+			.unwrap_or_else(|e| e.into_inner());
+		set_exchange_rates(rates);
+		let eval = |input| eval(input, true, Unit::Celsius, false).unwrap().to_string();
+		assert_eq!(eval("$10 to eur"), "8.0 EUR");
+		assert_eq!(eval("10 gbp to usd"), "12.5 USD");
+		assert_eq!(eval("1000 jpy to gbp"), "5.12 GBP");
+		assert_eq!(eval("eur 8 + $2"), "12 USD");
+		assert_eq!(eval("1 sek to nok"), "0.95 NOK");
+		assert_eq!(eval("€20 / €5"), "4");
+		let error = crate::eval("1 usd to chf", true, Unit::Celsius, false).unwrap_err();
+		assert_eq!(
+			error,
+			Error::MissingExchangeRate {
+				currency: Currency::new("chf").unwrap(),
+				span: Some(crate::Span::new(0, 12))
+			}
+		);
+		clear_exchange_rates();
+	}
+
+	#[test]
+	fn test_currency_codes() {
+		assert!(Currency::new("usd").unwrap().is_iso_4217());
+		assert!(Currency::new("XAU").unwrap().is_iso_4217());
+		assert!(!Currency::new("abc").unwrap().is_iso_4217());
+
+		// only ISO 4217 codes with an exchange rate are currencies, so other
+		// 3 letter words can still be variables
+		let json = r#"{ "base": "USD", "timestamp": 0, "rates": { "ABC": 2, "UZS": 12000 } }"#;
+		let _lock = EXCHANGE_RATES_TEST_LOCK
+			.lock()
+			.unwrap_or_else(|e| e.into_inner());
+		set_exchange_rates(ExchangeRates::from_json(json).unwrap());
+		let eval = |input| eval(input, true, Unit::Celsius, false);
+		assert_eq!(eval("1 usd to uzs").unwrap().to_string(), "12000 UZS");
+		assert!(matches!(
+			eval("abc * 2"),
+			Err(Error::UndefinedVariable { .. })
+		));
+		clear_exchange_rates();
+		assert!(matches!(eval("1 uzs"), Err(Error::UnknownWord { .. })));
+	}
+}
@@ -0,0 +1,318 @@
+use crate::timezone::TimeZone;
+use crate::units::{self, Unit, UnitType};
+use crate::{floor, to_integer, Error, Number};
+use chrono::{DateTime, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use decimal::d128;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Splits seconds into whole seconds, rounded down, and nanoseconds
+fn split_seconds(seconds: d128) -> Option<(i64, u32)> {
+	let whole = floor(seconds);
+	let nanoseconds = ((seconds - whole) * d128!(1000000000)).quantize(d128!(1));
+	let whole = i64::try_from(to_integer(whole)?).ok()?;
+	let nanoseconds = u32::try_from(to_integer(nanoseconds)?).ok()?;
+	Some((whole, nanoseconds.min(999_999_999)))
+}
+
+/// The date and time in UTC of a value in seconds since the Unix epoch, or
+/// `None` if it's out of range
+pub fn to_datetime(seconds: d128) -> Option<NaiveDateTime> {
+	let (whole, nanoseconds) = split_seconds(seconds)?;
+	DateTime::from_timestamp(whole, nanoseconds).map(|datetime| datetime.naive_utc())
+}
+
+/// The number of seconds since the Unix epoch of a date and time in UTC
+pub fn from_datetime(datetime: NaiveDateTime) -> d128 {
+	let datetime = datetime.and_utc();
+	let nanoseconds = d128::from(datetime.timestamp_subsec_nanos()) / d128!(1000000000);
+	d128::from(datetime.timestamp()) + nanoseconds
+}
+
+/// Parses an ISO 8601 date like `2026-03-01`, or a date and time like
+/// `2026-03-01T12:00` or `2026-03-01 12:00:30.5Z`, into seconds since the
+/// Unix epoch. Times are in UTC.
+pub fn parse_iso(input: &str) -> Option<d128> {
+	let (date, time) = match input.split_once(['T', 't', ' ']) {
+		Some((date, time)) => (date, Some(time.trim_end_matches(['Z', 'z']))),
+		None => (input, None),
+	};
+	let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+	let time = match time {
+		Some(time) => NaiveTime::parse_from_str(time, "%H:%M:%S%.f")
+			.or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+			.ok()?,
+		None => NaiveTime::MIN,
+	};
+	Some(from_datetime(date.and_time(time)))
+}
+
+/// The current time in whole seconds since the Unix epoch, or the time of
+/// `clock` if it's set
+pub(crate) fn now(clock: Option<SystemTime>) -> d128 {
+	match clock
+		.unwrap_or_else(SystemTime::now)
+		.duration_since(UNIX_EPOCH)
+	{
+		Ok(duration) => d128::from(duration.as_secs()),
+		Err(e) => -d128::from(e.duration().as_secs()),
+	}
+}
+
+/// The start of the current day in UTC, in seconds since the Unix epoch
+pub(crate) fn today(clock: Option<SystemTime>) -> d128 {
+	let day = d128!(86400);
+	floor(now(clock) / day) * day
+}
+
+/// Formats a number of seconds in hours, minutes and seconds, like `1:30:15`
+/// or `-0:00:01.5`
+pub(crate) fn format_clock(seconds: d128) -> String {
+	let sign = if seconds.is_negative() { "-" } else { "" };
+	let seconds = seconds.abs();
+	let hours = floor(seconds / d128!(3600));
+	let minutes = floor((seconds - hours * d128!(3600)) / d128!(60));
+	let seconds = seconds - hours * d128!(3600) - minutes * d128!(60);
+	let whole_seconds = floor(seconds);
+	// 0.50 becomes .5
+	let fraction = (seconds - whole_seconds).reduce() + d128!(0);
+	let fraction = match fraction.is_zero() {
+		true => String::new(),
+		false => fraction.to_string().trim_start_matches('0').to_string(),
+	};
+	format!(
+		"{}{}:{:0>2}:{:0>2}{}",
+		sign, hours, minutes, whole_seconds, fraction
+	)
+}
+
+/// The local date and time of a value in seconds since the Unix epoch. It's
+/// in the time zone of a [`Zoned`](Unit::Zoned) unit, and otherwise in UTC.
+fn to_local(seconds: d128, unit: Unit) -> Option<NaiveDateTime> {
+	let utc = to_datetime(seconds)?;
+	match unit {
+		Unit::Zoned(zone) => utc.checked_add_offset(zone.offset(utc)),
+		_ => Some(utc),
+	}
+}
+
+/// The number of seconds since the Unix epoch of a local date and time, in
+/// the time zone of a [`Zoned`](Unit::Zoned) unit, and otherwise in UTC
+fn from_local(local: NaiveDateTime, unit: Unit) -> Result<d128, Error> {
+	match unit {
+		Unit::Zoned(zone) => match zone.to_utc(local) {
+			Some(utc) => Ok(from_datetime(utc)),
+			None => Err(Error::InvalidTime {
+				time: format!("{} {}", local.format("%Y-%m-%d %H:%M:%S"), zone),
+				span: None,
+			}),
+		},
+		_ => Ok(from_datetime(local)),
+	}
+}
+
+/// Formats a [`Date`](Unit::Date) like `2026-03-01`, a
+/// [`DateTime`](Unit::DateTime) like `2026-03-01 12:00:00 UTC`, or a
+/// [`Zoned`](Unit::Zoned) date and time like `2026-03-01 13:00:00 CET`.
+/// Returns `None` for other units and values that are out of range.
+pub(crate) fn format(seconds: d128, unit: Unit) -> Option<String> {
+	let local = to_local(seconds, unit)?;
+	let zone = match unit {
+		Unit::Date => return Some(local.format("%Y-%m-%d").to_string()),
+		Unit::DateTime => "UTC".to_string(),
+		Unit::Zoned(zone) => zone.abbreviation(to_datetime(seconds)?),
+		_ => return None,
+	};
+	let format = match local.nanosecond() {
+		0 => "%Y-%m-%d %H:%M:%S",
+		_ => "%Y-%m-%d %H:%M:%S%.f",
+	};
+	Some(format!("{} {}", local.format(format), zone))
+}
+
+/// The number of months in a calendar unit of [`Time`](UnitType::Time)
+fn months_in(unit: Unit) -> Option<i128> {
+	match unit {
+		Unit::Month => Some(1),
+		Unit::Quarter => Some(3),
+		Unit::Year => Some(12),
+		Unit::Decade => Some(120),
+		Unit::Century => Some(1200),
+		Unit::Millenium => Some(12000),
+		_ => None,
+	}
+}
+
+/// Adds a [`Time`](UnitType::Time) to a date.
+///
+/// Whole months, quarters, years, decades, centuries and millenia are added
+/// to the calendar, so `2026-01-31 + 1 month` is `2026-02-28`. Other values
+/// are added as an exact number of seconds. A [`Date`](Unit::Date) becomes a
+/// [`DateTime`](Unit::DateTime) if the result isn't at midnight.
+pub fn add_duration(date: Number, duration: Number) -> Result<Number, Error> {
+	let out_of_range = || Error::DateOutOfRange { span: None };
+	let months = months_in(duration.unit).and_then(|months| {
+		let months = to_integer(duration.value)? * months;
+		Some((u32::try_from(months.abs()).ok()?, months.is_negative()))
+	});
+	let value = match months {
+		Some((months, negative)) => {
+			let datetime = to_local(date.value, date.unit).ok_or_else(out_of_range)?;
+			let result = match negative {
+				false => datetime.checked_add_months(Months::new(months)),
+				true => datetime.checked_sub_months(Months::new(months)),
+			};
+			from_local(result.ok_or_else(out_of_range)?, date.unit)?
+		}
+		None => date.value + units::to_si(&duration),
+	};
+	let datetime = to_datetime(value).ok_or_else(out_of_range)?;
+	let unit = match date.unit {
+		Unit::Date if datetime.time() != NaiveTime::MIN => Unit::DateTime,
+		unit => unit,
+	};
+	Ok(Number::new(value, unit))
+}
+
+/// The [`Time`](UnitType::Time) between two dates. The difference between two
+/// [`Date`](Unit::Date)s is in days, and other differences are in an ideal
+/// unit, like hours.
+pub fn difference(left: Number, right: Number) -> Number {
+	let seconds = left.value - right.value;
+	if left.unit == Unit::Date && right.unit == Unit::Date {
+		Number::new(seconds / d128!(86400), Unit::Day)
+	} else {
+		units::to_ideal_unit(Number::new(seconds, Unit::Second))
+	}
+}
+
+/// A date or time of day in a time zone, like `2026-03-01T14:00 Europe/Berlin`
+/// or `3pm PST`.
+///
+/// A [`Date`](Unit::Date) or [`DateTime`](Unit::DateTime) is read as local
+/// time in the zone, and other points in time are shown in the zone. A
+/// [`Time`](UnitType::Time) is a time of day on the current date in the
+/// zone, using `clock` as the current time if it's set.
+pub(crate) fn in_time_zone(
+	number: Number,
+	zone: TimeZone,
+	clock: Option<SystemTime>,
+) -> Result<Number, Error> {
+	let out_of_range = || Error::DateOutOfRange { span: None };
+	let unit = Unit::Zoned(zone);
+	let local = match (number.unit, number.unit.category()) {
+		(Unit::Date | Unit::DateTime, _) => to_datetime(number.value).ok_or_else(out_of_range)?,
+		(_, UnitType::Timestamp) => return Ok(Number::new(number.value, unit)),
+		(_, UnitType::Time) => {
+			let today = to_local(now(clock), unit).ok_or_else(out_of_range)?.date();
+			let seconds = today.and_time(NaiveTime::MIN).and_utc().timestamp();
+			to_datetime(d128::from(seconds) + units::to_si(&number)).ok_or_else(out_of_range)?
+		}
+		_ => {
+			return Err(Error::CannotConvert {
+				from: number.unit,
+				to: unit,
+				span: None,
+			})
+		}
+	};
+	Ok(Number::new(from_local(local, unit)?, unit))
+}
+
+/// Returns true if the [`Unit`] is a point in time, like a [`Date`](Unit::Date)
+pub(crate) fn is_timestamp(unit: Unit) -> bool {
+	unit.category() == UnitType::Timestamp
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{eval_with_context, Context};
+	use std::time::Duration;
+
+	#[test]
+	fn test_datetime() {
+		// 2026-10-18 15:30:00 UTC
+		let mut context = Context {
+			now: Some(UNIX_EPOCH + Duration::from_secs(1792337400)),
+			..Context::default()
+		};
+		let mut eval = |input| {
+			eval_with_context(input, &mut context, true, Unit::Celsius, false)
+				.unwrap()
+				.to_string()
+		};
+		assert_eq!(eval("2026-03-01 + 90 days"), "2026-05-30");
+		assert_eq!(eval("2026-03-01 + 36 h"), "2026-03-02 12:00:00 UTC");
+		assert_eq!(eval("2026-01-31 + 1 month"), "2026-02-28");
+		assert_eq!(eval("2024-02-29 + 1 year"), "2025-02-28");
+		assert_eq!(eval("2026-03-31 - 1 quarter"), "2025-12-31");
+		assert_eq!(eval("1 decade + 2026-03-01"), "2036-03-01");
+		assert_eq!(eval("2026-12-25 - 2026-10-18 to days"), "68 Day");
+		assert_eq!(eval("2026-03-01T12:00 - 2026-03-01 06:30:00"), "5.5 Hour");
+		assert_eq!(eval("today"), "2026-10-18");
+		assert_eq!(eval("today + 6 weeks"), "2026-11-29");
+		assert_eq!(eval("now"), "2026-10-18 15:30:00 UTC");
+		assert_eq!(eval("now to unix"), "1792337400 UnixTimestamp");
+		assert_eq!(
+			eval("1700000000 unix to datetime"),
+			"2023-11-14 22:13:20 UTC"
+		);
+		assert_eq!(eval("now - 2026-10-18"), "15.5 Hour");
+		assert_eq!(eval("2026-03-01T12:00:00.25z to date"), "2026-03-01");
+		assert_eq!(
+			eval("2026-03-01T12:00:00.25z"),
+			"2026-03-01 12:00:00.250 UTC"
+		);
+
+		let error =
+			|input| eval_with_context(input, &mut Context::default(), true, Unit::Celsius, false);
+		assert!(matches!(
+			error("2026-02-30"),
+			Err(Error::InvalidDate { .. })
+		));
+		assert!(matches!(
+			error("2026-03-01 + 2026-03-01"),
+			Err(Error::IncompatibleUnits { .. })
+		));
+		assert!(matches!(
+			error("2 * today"),
+			Err(Error::IncompatibleUnits { .. })
+		));
+		assert!(matches!(
+			error("today + 300000 years"),
+			Err(Error::DateOutOfRange { .. })
+		));
+	}
+
+	#[test]
+	fn test_clock_times() {
+		let eval =
+			|input| eval_with_context(input, &mut Context::default(), true, Unit::Celsius, false);
+		let eval_ok = |input| eval(input).unwrap().to_string();
+		assert_eq!(eval_ok("1:30:15 + 45:00 to h:mm:ss"), "2:15:15");
+		assert_eq!(eval_ok("1:30 + 45:00 to h:mm:ss"), "46:30:00");
+		assert_eq!(eval_ok("-2h 15min"), "-135 Minute");
+		assert_eq!(eval_ok("1 h - -2h 15min to h:mm:ss"), "3:15:00");
+		assert_eq!(eval_ok("2h 15min + 45min"), "180 Minute");
+		assert_eq!(eval_ok("2h 15m 30s to h:mm:ss"), "2:15:30");
+		assert_eq!(eval_ok("1:30.5 * 3 to h:mm:ss"), "0:04:31.5");
+		assert_eq!(eval_ok("-100 min to h:mm:ss"), "-1:40:00");
+		assert_eq!(eval_ok("1 day 1 s in hh:mm:ss"), "24:00:01");
+		assert_eq!(eval_ok("6'4\" to cm"), "193.04 Centimeter");
+		assert_eq!(eval_ok("1 kg 250 g"), "1250 Gram");
+		assert_eq!(eval_ok("2 * 1h 30min"), "180 Minute");
+		assert_eq!(eval_ok("3 h / 1h 30min"), "2");
+		assert_eq!(eval_ok("2h mod 1h 30min"), "30 Minute");
+		assert_eq!(eval_ok("2 * -1h 30min"), "-180 Minute");
+		assert_eq!(eval_ok("1h 30min * 2"), "180 Minute");
+		assert_eq!(eval_ok("45:00"), "45:00:00");
+		assert_eq!(eval_ok("3pm + 1h"), "16:00:00");
+		assert_eq!(eval_ok("1:30:15 + 45:00"), "2:15:15");
+		assert_eq!(eval_ok("1:30:15 to s"), "5415 Second");
+
+		assert!(matches!(
+			eval("5 km to h:mm:ss"),
+			Err(Error::CannotConvert { .. })
+		));
+	}
+}
@@ -0,0 +1,188 @@
+use crate::Error;
+use std::fmt::{self, Debug, Display};
+use std::ops::{Div, Mul};
+
+/// The number of base dimensions in [`Dimensions`]
+const BASE_DIMENSIONS: usize = 8;
+
+/// Symbols of the SI base unit of each base dimension, in the order of
+/// [`Dimensions::exponents`]
+const SYMBOLS: [&str; BASE_DIMENSIONS] = ["m", "kg", "s", "A", "K", "mol", "cd", "bit"];
+
+/// The order base dimensions are displayed in, so force is `kg·m/s²`
+const DISPLAY_ORDER: [usize; BASE_DIMENSIONS] = [1, 0, 2, 3, 4, 5, 6, 7];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+/// The dimensions of a unit, as exponents of the SI base dimensions (length,
+/// mass, time, electric current, temperature, amount of substance and
+/// luminous intensity) and information.
+///
+/// For example, speed is length¹ time⁻¹ and force is mass¹ length¹ time⁻².
+/// Multiplying two units adds their exponents, and dividing subtracts them.
+/// Exponents are `i8`s, so `*`, `/` and [`pow`](Dimensions::pow) panic if one
+/// overflows. Use [`checked_mul`](Dimensions::checked_mul),
+/// [`checked_div`](Dimensions::checked_div) and
+/// [`checked_pow`](Dimensions::checked_pow) for dimensions that come from user
+/// input.
+///
+/// Example:
+/// ```rust
+/// use cpc::dimensions::Dimensions;
+///
+/// let force = Dimensions::MASS * Dimensions::LENGTH / Dimensions::TIME.pow(2);
+/// assert_eq!(force.to_string(), "kg·m/s²");
+/// assert_eq!(Dimensions::LENGTH.pow(2).root(2), Some(Dimensions::LENGTH));
+/// assert_eq!(Dimensions::LENGTH.root(2), None);
+/// assert!(Dimensions::LENGTH.checked_pow(100).unwrap().checked_mul(Dimensions::LENGTH.pow(100)).is_err());
+/// ```
+pub struct Dimensions {
+	/// Exponents of length, mass, time, electric current, temperature, amount
+	/// of substance, luminous intensity and information
+	pub exponents: [i8; BASE_DIMENSIONS],
+}
+
+impl Dimensions {
+	/// No dimensions, like a plain number
+	pub const NONE: Dimensions = Dimensions::base(None);
+	pub const LENGTH: Dimensions = Dimensions::base(Some(0));
+	pub const MASS: Dimensions = Dimensions::base(Some(1));
+	pub const TIME: Dimensions = Dimensions::base(Some(2));
+	pub const CURRENT: Dimensions = Dimensions::base(Some(3));
+	pub const TEMPERATURE: Dimensions = Dimensions::base(Some(4));
+	pub const AMOUNT: Dimensions = Dimensions::base(Some(5));
+	pub const LUMINOSITY: Dimensions = Dimensions::base(Some(6));
+	pub const INFORMATION: Dimensions = Dimensions::base(Some(7));
+
+	/// The dimensions with an exponent of 1 at `index`, or no dimensions
+	const fn base(index: Option<usize>) -> Dimensions {
+		let mut exponents = [0; BASE_DIMENSIONS];
+		if let Some(index) = index {
+			exponents[index] = 1;
+		}
+		Dimensions { exponents }
+	}
+
+	/// Raise the dimensions to an integer power, so `LENGTH.pow(2)` is area.
+	/// Panics if an exponent overflows.
+	pub fn pow(self, exponent: i8) -> Dimensions {
+		self.checked_pow(exponent).unwrap()
+	}
+
+	/// Raise the dimensions to an integer power. Returns
+	/// [`Error::DimensionOverflow`] if an exponent doesn't fit in an `i8`.
+	pub fn checked_pow(self, exponent: i8) -> Result<Dimensions, Error> {
+		let mut exponents = self.exponents;
+		for e in exponents.iter_mut() {
+			*e = e
+				.checked_mul(exponent)
+				.ok_or(Error::DimensionOverflow { span: None })?;
+		}
+		Ok(Dimensions { exponents })
+	}
+
+	/// Multiply the dimensions, which adds their exponents. Returns
+	/// [`Error::DimensionOverflow`] if an exponent doesn't fit in an `i8`.
+	pub fn checked_mul(self, other: Dimensions) -> Result<Dimensions, Error> {
+		let mut exponents = self.exponents;
+		for (e, other) in exponents.iter_mut().zip(other.exponents.iter()) {
+			*e = e
+				.checked_add(*other)
+				.ok_or(Error::DimensionOverflow { span: None })?;
+		}
+		Ok(Dimensions { exponents })
+	}
+
+	/// Divide the dimensions, which subtracts their exponents. Returns
+	/// [`Error::DimensionOverflow`] if an exponent doesn't fit in an `i8`.
+	pub fn checked_div(self, other: Dimensions) -> Result<Dimensions, Error> {
+		self.checked_mul(other.checked_pow(-1)?)
+	}
+
+	/// The `degree`th root of the dimensions, so the square root of area is
+	/// length. Returns `None` if an exponent isn't divisible by `degree`.
+	pub fn root(self, degree: i8) -> Option<Dimensions> {
+		let mut exponents = self.exponents;
+		for e in exponents.iter_mut() {
+			if e.checked_rem(degree)? != 0 {
+				return None;
+			}
+			*e = e.checked_div(degree)?;
+		}
+		Some(Dimensions { exponents })
+	}
+
+	/// Returns true if all exponents are zero
+	pub fn is_dimensionless(&self) -> bool {
+		self.exponents.iter().all(|e| *e == 0)
+	}
+}
+
+impl Mul for Dimensions {
+	type Output = Dimensions;
+	// multiplying units adds the exponents of their dimensions
+	fn mul(self, other: Dimensions) -> Dimensions {
+		self.checked_mul(other).unwrap()
+	}
+}
+
+impl Div for Dimensions {
+	type Output = Dimensions;
+	fn div(self, other: Dimensions) -> Dimensions {
+		self.checked_div(other).unwrap()
+	}
+}
+
+/// Formats a unit with the absolute value of an exponent, like `s²`
+pub(crate) fn format_power(symbol: &str, exponent: u8) -> String {
+	if exponent == 1 {
+		return symbol.to_string();
+	}
+	let superscript: String = exponent
+		.to_string()
+		.chars()
+		.map(|c| match c {
+			'0' => '⁰',
+			'1' => '¹',
+			'2' => '²',
+			'3' => '³',
+			'4' => '⁴',
+			'5' => '⁵',
+			'6' => '⁶',
+			'7' => '⁷',
+			'8' => '⁸',
+			'9' => '⁹',
+			c => c,
+		})
+		.collect();
+	format!("{}{}", symbol, superscript)
+}
+
+impl Display for Dimensions {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut numerator = Vec::new();
+		let mut denominator = Vec::new();
+		for index in DISPLAY_ORDER {
+			let exponent = self.exponents[index];
+			if exponent > 0 {
+				numerator.push(format_power(SYMBOLS[index], exponent.unsigned_abs()));
+			} else if exponent < 0 {
+				denominator.push(format_power(SYMBOLS[index], exponent.unsigned_abs()));
+			}
+		}
+		let numerator = match numerator.len() {
+			0 => "1".to_string(),
+			_ => numerator.join("·"),
+		};
+		match denominator.len() {
+			0 => write!(f, "{}", numerator),
+			1 => write!(f, "{}/{}", numerator, denominator[0]),
+			_ => write!(f, "{}/({})", numerator, denominator.join("·")),
+		}
+	}
+}
+
+impl Debug for Dimensions {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		Display::fmt(self, f)
+	}
+}
@@ -0,0 +1,407 @@
+use crate::currency::Currency;
+use crate::units::Unit;
+use crate::{FunctionIdentifier, Operator, Token};
+use decimal::d128;
+use std::fmt::{self, Display};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A range of bytes in the input string, used to point at the part of the
+/// input an [`Error`] is about.
+pub struct Span {
+	/// Byte offset of the first character
+	pub start: usize,
+	/// Byte offset after the last character
+	pub end: usize,
+}
+
+impl Span {
+	pub const fn new(start: usize, end: usize) -> Span {
+		Span { start, end }
+	}
+	/// The smallest [`Span`] that covers both `self` and `other`
+	pub fn to(self, other: Span) -> Span {
+		Span::new(self.start.min(other.start), self.end.max(other.end))
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An error returned when lexing, parsing or evaluating fails.
+///
+/// Every variant carries a [`Span`] pointing at the offending part of the
+/// input, when one is known.
+///
+/// Example:
+/// ```rust
+/// use cpc::{eval, Error};
+/// use cpc::units::Unit;
+///
+/// match eval("3 km + 2 kg", true, Unit::Celsius, false) {
+///     Err(Error::IncompatibleUnits { left, right, .. }) => {
+///         assert_eq!(left, Unit::Kilometer);
+///         assert_eq!(right, Unit::Kilogram);
+///     }
+///     _ => unreachable!(),
+/// }
+/// ```
+pub enum Error {
+	/// The input was empty, or only contained whitespace
+	EmptyInput,
+	/// A character that is not part of any token, like `@`
+	InvalidCharacter {
+		character: String,
+		span: Option<Span>,
+	},
+	/// A word that is not a unit, function, constant or keyword
+	UnknownWord { word: String, span: Option<Span> },
+	/// A number that could not be represented as a [`struct@d128`]
+	InvalidNumber { number: String, span: Option<Span> },
+	/// A comma in function arguments that could be a thousands separator or
+	/// separate arguments, like the second one in `max(1, 2,000)`
+	AmbiguousComma { span: Option<Span> },
+	/// A token that isn't allowed at its position. `expected` describes what
+	/// was expected instead. `token` is `None` at the end of the input.
+	UnexpectedToken {
+		token: Option<Token>,
+		expected: &'static str,
+		span: Option<Span>,
+	},
+	/// Two units can't be combined with an operator, like `1 km + 1 kg`
+	IncompatibleUnits {
+		left: Unit,
+		right: Unit,
+		op: Operator,
+		span: Option<Span>,
+	},
+	/// A unit can't be converted into another unit, like `1 km to kg`
+	CannotConvert {
+		from: Unit,
+		to: Unit,
+		span: Option<Span>,
+	},
+	/// A fuel economy that isn't positive converted to or from the reciprocal
+	/// l/100km, like `0 mpg to l/100km`
+	InvalidFuelEconomy { value: d128, span: Option<Span> },
+	/// The right side of `to` is not a unit
+	ExpectedUnit { span: Option<Span> },
+	/// A unit was used where only a plain number is allowed, like `log(1 km)`
+	UnexpectedUnit { unit: Unit, span: Option<Span> },
+	/// A function was called with a value it isn't defined for, like `sqrt(-1)`
+	DomainError {
+		function: FunctionIdentifier,
+		value: d128,
+		span: Option<Span>,
+	},
+	/// Factorial of a value that isn't an integer from 0 to 1000
+	FactorialOutOfRange { value: d128, span: Option<Span> },
+	/// A named number followed by a smaller one, like `1 thousand million`
+	NamedNumberOrder { span: Option<Span> },
+	/// An [`AstNode`](crate::parser::AstNode) without the children its token needs
+	MalformedAst { token: Token, span: Option<Span> },
+	/// A variable that has no value, like `ans` before anything was evaluated
+	UndefinedVariable { name: String, span: Option<Span> },
+	/// An assignment to a name that's already a unit, constant or keyword, like `m = 5`
+	ReservedName { name: String, span: Option<Span> },
+	/// A base outside of 2 to 36, like `to base 40`
+	InvalidBase { base: String, span: Option<Span> },
+	/// Converting a negative or non-integer value to a base, like `-1 to hex`
+	InvalidBaseConversion {
+		value: d128,
+		base: u32,
+		span: Option<Span>,
+	},
+	/// A bitwise operation on a value that isn't an integer, like `1.5 and 1`
+	NotAnInteger { value: d128, span: Option<Span> },
+	/// A negative shift amount, like `1 << -1`
+	NegativeShift { value: d128, span: Option<Span> },
+	/// A function called with too few or too many arguments, like `atan2(1)`
+	WrongArgumentCount {
+		function: FunctionIdentifier,
+		found: usize,
+		span: Option<Span>,
+	},
+	/// A unit raised to a power that would give it fractional dimensions,
+	/// like `(4 m2)^0.25`
+	FractionalDimensions {
+		unit: Unit,
+		exponent: d128,
+		span: Option<Span>,
+	},
+	/// A unit whose dimensions have an exponent outside the range of an `i8`,
+	/// like `(2 m)^100 * (2 m)^100`
+	DimensionOverflow { span: Option<Span> },
+	/// A conversion between currencies without an exchange rate for one of
+	/// them, like `1 USD to EUR` before exchange rates are set
+	MissingExchangeRate {
+		currency: Currency,
+		span: Option<Span>,
+	},
+	/// Exchange rates that couldn't be read, like a file with invalid JSON
+	InvalidExchangeRates { reason: String },
+	/// A date that doesn't exist, like `2026-02-30`
+	InvalidDate { date: String, span: Option<Span> },
+	/// A date outside of the range that can be represented, like `today + 300000 years`
+	DateOutOfRange { span: Option<Span> },
+	/// A time of day that doesn't exist, like `25:00`, or a local time that
+	/// was skipped when clocks were set forward
+	InvalidTime { time: String, span: Option<Span> },
+	/// Units to split a value into that aren't of the same type or not in
+	/// descending order, like `in+ft`
+	InvalidMixedUnits { span: Option<Span> },
+	/// A time zone abbreviation that's used for different offsets, like `CST`
+	AmbiguousTimeZone {
+		abbreviation: String,
+		/// The zones that use the abbreviation, with their offsets
+		candidates: Vec<String>,
+		span: Option<Span>,
+	},
+}
+
+impl Error {
+	/// The [`Span`] of the input this error is about, if known
+	pub fn span(&self) -> Option<Span> {
+		match self {
+			Error::EmptyInput | Error::InvalidExchangeRates { .. } => None,
+			Error::InvalidCharacter { span, .. }
+			| Error::UnknownWord { span, .. }
+			| Error::InvalidNumber { span, .. }
+			| Error::AmbiguousComma { span }
+			| Error::UnexpectedToken { span, .. }
+			| Error::IncompatibleUnits { span, .. }
+			| Error::CannotConvert { span, .. }
+			| Error::InvalidFuelEconomy { span, .. }
+			| Error::ExpectedUnit { span }
+			| Error::UnexpectedUnit { span, .. }
+			| Error::DomainError { span, .. }
+			| Error::FactorialOutOfRange { span, .. }
+			| Error::NamedNumberOrder { span }
+			| Error::MalformedAst { span, .. }
+			| Error::UndefinedVariable { span, .. }
+			| Error::ReservedName { span, .. }
+			| Error::InvalidBase { span, .. }
+			| Error::InvalidBaseConversion { span, .. }
+			| Error::NotAnInteger { span, .. }
+			| Error::NegativeShift { span, .. }
+			| Error::WrongArgumentCount { span, .. }
+			| Error::FractionalDimensions { span, .. }
+			| Error::DimensionOverflow { span }
+			| Error::MissingExchangeRate { span, .. }
+			| Error::InvalidDate { span, .. }
+			| Error::DateOutOfRange { span }
+			| Error::InvalidTime { span, .. }
+			| Error::AmbiguousTimeZone { span, .. }
+			| Error::InvalidMixedUnits { span } => *span,
+		}
+	}
+
+	/// Sets the [`Span`] of this error, unless it already has one.
+	///
+	/// Functions like [`units::add`](crate::units::add) don't know where
+	/// their operands came from, so the caller fills in the span afterwards.
+	pub fn with_span(mut self, new_span: Span) -> Error {
+		match &mut self {
+			Error::EmptyInput | Error::InvalidExchangeRates { .. } => {}
+			Error::InvalidCharacter { span, .. }
+			| Error::UnknownWord { span, .. }
+			| Error::InvalidNumber { span, .. }
+			| Error::AmbiguousComma { span }
+			| Error::UnexpectedToken { span, .. }
+			| Error::IncompatibleUnits { span, .. }
+			| Error::CannotConvert { span, .. }
+			| Error::InvalidFuelEconomy { span, .. }
+			| Error::ExpectedUnit { span }
+			| Error::UnexpectedUnit { span, .. }
+			| Error::DomainError { span, .. }
+			| Error::FactorialOutOfRange { span, .. }
+			| Error::NamedNumberOrder { span }
+			| Error::MalformedAst { span, .. }
+			| Error::UndefinedVariable { span, .. }
+			| Error::ReservedName { span, .. }
+			| Error::InvalidBase { span, .. }
+			| Error::InvalidBaseConversion { span, .. }
+			| Error::NotAnInteger { span, .. }
+			| Error::NegativeShift { span, .. }
+			| Error::WrongArgumentCount { span, .. }
+			| Error::FractionalDimensions { span, .. }
+			| Error::DimensionOverflow { span }
+			| Error::MissingExchangeRate { span, .. }
+			| Error::InvalidDate { span, .. }
+			| Error::DateOutOfRange { span }
+			| Error::InvalidTime { span, .. }
+			| Error::AmbiguousTimeZone { span, .. }
+			| Error::InvalidMixedUnits { span } => {
+				if span.is_none() {
+					*span = Some(new_span);
+				}
+			}
+		}
+		self
+	}
+}
+
+fn operator_verb(op: &Operator) -> &'static str {
+	match op {
+		Operator::Plus => "add",
+		Operator::Minus => "subtract",
+		Operator::Multiply => "multiply",
+		Operator::Divide => "divide",
+		Operator::Modulo => "modulo",
+		Operator::Caret => "raise",
+		Operator::BitAnd => "and",
+		Operator::BitOr => "or",
+		Operator::BitXor => "xor",
+		Operator::BitNot => "not",
+		Operator::ShiftLeft | Operator::ShiftRight => "shift",
+		_ => "combine",
+	}
+}
+
+impl Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Error::EmptyInput => write!(f, "Input was empty"),
+			Error::InvalidCharacter { character, .. } => {
+				write!(f, "Invalid character: {}", character)
+			}
+			Error::UnknownWord { word, .. } => write!(f, "Invalid string: {}", word),
+			Error::InvalidNumber { number, .. } => write!(f, "Invalid number: {}", number),
+			Error::AmbiguousComma { .. } => write!(
+				f,
+				"Ambiguous comma, write the number without a thousands separator or put a space after the comma"
+			),
+			Error::UnexpectedToken {
+				token: Some(token),
+				expected,
+				..
+			} => {
+				write!(f, "Unexpected token {:?}, expected {}", token, expected)
+			}
+			Error::UnexpectedToken {
+				token: None,
+				expected,
+				..
+			} => {
+				write!(f, "Unexpected end of input, expected {}", expected)
+			}
+			Error::IncompatibleUnits {
+				left, right, op, ..
+			} => match op {
+				Operator::Minus => write!(f, "Cannot subtract {:?} by {:?}", left, right),
+				Operator::BitAnd | Operator::BitOr | Operator::BitXor => write!(
+					f,
+					"Cannot perform bitwise {} on {:?} and {:?}",
+					operator_verb(op),
+					left,
+					right
+				),
+				Operator::Divide
+				| Operator::Modulo
+				| Operator::ShiftLeft
+				| Operator::ShiftRight => {
+					write!(f, "Cannot {} {:?} by {:?}", operator_verb(op), left, right)
+				}
+				Operator::Caret => {
+					write!(f, "Cannot raise {:?} to the power of {:?}", left, right)
+				}
+				_ => write!(f, "Cannot {} {:?} and {:?}", operator_verb(op), left, right),
+			},
+			Error::CannotConvert { from, to, .. } => {
+				write!(f, "Cannot convert from {:?} to {:?}", from, to)
+			}
+			Error::InvalidFuelEconomy { value, .. } => write!(
+				f,
+				"Cannot convert a fuel economy of {} to or from l/100km, it must be positive",
+				value
+			),
+			Error::ExpectedUnit { .. } => {
+				write!(f, "Right side of the To operator needs to be a unit")
+			}
+			Error::UnexpectedUnit { unit, .. } => {
+				write!(f, "Expected a number without a unit, found {:?}", unit)
+			}
+			Error::DomainError {
+				function, value, ..
+			} => write!(
+				f,
+				"{}() is not defined for {}",
+				format!("{:?}", function).to_lowercase(),
+				value
+			),
+			Error::FactorialOutOfRange { .. } => {
+				write!(f, "Can only perform factorial on integers from 0 to 1000")
+			}
+			Error::NamedNumberOrder { .. } => {
+				write!(f, "A named number can't be followed by a smaller one")
+			}
+			Error::MalformedAst { token, .. } => {
+				write!(f, "Token {:?} is missing children", token)
+			}
+			Error::UndefinedVariable { name, .. } => write!(f, "Undefined variable: {}", name),
+			Error::ReservedName { name, .. } => {
+				write!(f, "Cannot assign to {}, since it's a unit or keyword", name)
+			}
+			Error::InvalidBase { base, .. } => {
+				write!(f, "Invalid base: {}. Must be from 2 to 36", base)
+			}
+			Error::InvalidBaseConversion { value, base, .. } => write!(
+				f,
+				"Cannot convert {} to base {}, only non-negative integers can be converted",
+				value, base
+			),
+			Error::NotAnInteger { value, .. } => {
+				write!(f, "Bitwise operations need integers, found {}", value)
+			}
+			Error::NegativeShift { value, .. } => write!(f, "Cannot shift by {}", value),
+			Error::WrongArgumentCount {
+				function, found, ..
+			} => {
+				let plural = |count: usize| if count == 1 { "argument" } else { "arguments" };
+				let expected = match function.arity() {
+					(min, Some(max)) if min == max => format!("{} {}", min, plural(min)),
+					(min, Some(max)) => format!("{} to {} arguments", min, max),
+					(min, None) => format!("at least {} {}", min, plural(min)),
+				};
+				write!(
+					f,
+					"{}() takes {}, found {}",
+					format!("{:?}", function).to_lowercase(),
+					expected,
+					found
+				)
+			}
+			Error::FractionalDimensions { unit, exponent, .. } => write!(
+				f,
+				"Cannot raise {:?} to the power of {}, the resulting unit would have fractional dimensions",
+				unit, exponent
+			),
+			Error::DimensionOverflow { .. } => {
+				write!(f, "The exponents of the resulting unit are too large")
+			}
+			Error::MissingExchangeRate { currency, .. } => {
+				write!(f, "No exchange rate for {}", currency)
+			}
+			Error::InvalidExchangeRates { reason } => {
+				write!(f, "Invalid exchange rates: {}", reason)
+			}
+			Error::InvalidDate { date, .. } => write!(f, "Invalid date: {}", date),
+			Error::DateOutOfRange { .. } => write!(f, "Date is out of range"),
+			Error::InvalidTime { time, .. } => write!(f, "Invalid time: {}", time),
+			Error::InvalidMixedUnits { .. } => write!(
+				f,
+				"Mixed units need 2 to {} lengths, masses, times or angles, from largest to smallest, like ft+in",
+				crate::units::MixedUnits::MAX
+			),
+			Error::AmbiguousTimeZone {
+				abbreviation,
+				candidates,
+				..
+			} => write!(
+				f,
+				"{} is ambiguous, it can be {}. Use a zone name instead",
+				abbreviation,
+				candidates.join(", ")
+			),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
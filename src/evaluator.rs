@@ -1,17 +1,30 @@
+use crate::datetime;
 use crate::lookup::{lookup_factorial, lookup_named_number};
 use crate::parser::AstNode;
-use crate::units::{add, convert, divide, modulo, multiply, pow, subtract, Unit, UnitType};
-use crate::Constant::{Pi, E};
+use crate::units::{
+	self, add, bitwise, bitwise_not, composite_unit, convert, convert_to_lowest, divide, modulo,
+	multiply, pow, subtract, MixedUnits, Unit, UnitType,
+};
+use crate::Constant::{Now, Pi, Today, E};
 use crate::FunctionIdentifier::*;
-use crate::Operator::{Caret, Divide, Minus, Modulo, Multiply, Plus};
+use crate::Operator::{
+	BitAnd, BitNot, BitOr, BitXor, Caret, Divide, Minus, Modulo, Multiply, Plus, ShiftLeft,
+	ShiftRight,
+};
 use crate::TextOperator::{Of, To};
 use crate::UnaryOperator::{Factorial, Percent};
-use crate::{Number, Token};
+use crate::{to_integer, Context, Error, Number, NumberFormat, Token};
 use decimal::d128;
 
 /// Evaluate an [`AstNode`] into a [`Number`]
-pub fn evaluate(ast: &AstNode) -> Result<Number, String> {
-	let answer = evaluate_node(ast)?;
+pub fn evaluate(ast: &AstNode) -> Result<Number, Error> {
+	evaluate_with_context(ast, &mut Context::default())
+}
+
+/// Evaluate an [`AstNode`] into a [`Number`], looking up `ans` and variables
+/// in a [`Context`]. Assignments are stored in the [`Context`].
+pub fn evaluate_with_context(ast: &AstNode, context: &mut Context) -> Result<Number, Error> {
+	let answer = evaluate_node(ast, context)?;
 	Ok(answer)
 }
 
@@ -28,8 +41,14 @@ pub fn factorial(input: d128) -> d128 {
 pub fn sqrt(input: d128) -> d128 {
 	let mut n = d128!(1);
 	let half = d128!(0.5);
-	for _ in 0..10 {
-		n = (n + input / n) * half;
+	// stop once the result converges, which takes longer the further the
+	// input is from 1
+	for _ in 0..1000 {
+		let next = (n + input / n) * half;
+		if next == n {
+			break;
+		}
+		n = next;
 	}
 	n
 }
@@ -46,6 +65,20 @@ pub fn cbrt(input: d128) -> d128 {
 	n
 }
 
+/// Returns the `degree`th root of a non-negative [`struct@d128`]
+pub fn root(input: d128, degree: d128) -> d128 {
+	let one = d128!(1);
+	let mut result = input.pow(one / degree);
+	// 1 / degree is usually rounded, so for integer degrees, use a few
+	// iterations of Newton's method to make the result exact
+	if to_integer(degree).is_some() && !input.is_zero() {
+		for _ in 0..3 {
+			result -= (result.pow(degree) - input) / (degree * result.pow(degree - one));
+		}
+	}
+	result
+}
+
 /// Returns the sine of a [`struct@d128`]
 pub fn sin(mut input: d128) -> d128 {
 	let pi = d128!(3.141592653589793238462643383279503);
@@ -86,10 +119,227 @@ pub fn tan(input: d128) -> d128 {
 	sin(input) / cos(input)
 }
 
+/// Returns true if `value`, the sine or cosine of `radians`, is zero. Since π
+/// isn't exact, `sin(180°)` and `cos(90°)` are off by up to about 1E-32, so
+/// those count as zero too. Values near an input of 0 are accurate, like
+/// `sin(1E-40)`, so only an exact 0 counts there.
+fn is_trig_zero(value: d128, radians: d128) -> bool {
+	let tolerance = d128!(1E-30);
+	value.is_zero() || (value.abs() < tolerance && radians.abs() > tolerance)
+}
+
+/// Returns the arctangent of a [`struct@d128`], in radians
+pub fn atan(input: d128) -> d128 {
+	let half_pi = d128!(1.570796326794896619231321691639751);
+	let one = d128!(1);
+	if input.is_negative() {
+		return -atan(-input);
+	}
+	if input > one {
+		return half_pi - atan(one / input);
+	}
+	// atan(x) = 2 * atan(x / (1 + sqrt(1 + x^2))), so shrink the input a few
+	// times to make the series below converge quickly
+	let mut x = input;
+	let mut multiplier = d128!(1);
+	for _ in 0..3 {
+		x = x / (one + sqrt(one + x * x));
+		multiplier *= d128!(2);
+	}
+	let x_squared = x * x;
+	let mut term = x;
+	let mut result = d128!(0);
+	for i_int in 0..24 {
+		let i = d128::from(i_int);
+		result += term / (d128!(2) * i + one);
+		term *= -x_squared;
+	}
+	multiplier * result
+}
+
+/// Returns the arcsine of a [`struct@d128`] from -1 to 1, in radians
+pub fn asin(input: d128) -> d128 {
+	let one = d128!(1);
+	d128!(2) * atan(input / (one + sqrt(one - input * input)))
+}
+
+/// Returns the arccosine of a [`struct@d128`] from -1 to 1, in radians
+pub fn acos(input: d128) -> d128 {
+	let half_pi = d128!(1.570796326794896619231321691639751);
+	half_pi - asin(input)
+}
+
+/// Returns the arctangent of `y / x` in radians, using the signs of both to
+/// pick the quadrant. Returns `None` if both are zero.
+pub fn atan2(y: d128, x: d128) -> Option<d128> {
+	let pi = d128!(3.141592653589793238462643383279503);
+	let half_pi = d128!(1.570796326794896619231321691639751);
+	let zero = d128!(0);
+	if x > zero {
+		Some(atan(y / x))
+	} else if x < zero && y.is_negative() {
+		Some(atan(y / x) - pi)
+	} else if x < zero {
+		Some(atan(y / x) + pi)
+	} else if y > zero {
+		Some(half_pi)
+	} else if y < zero {
+		Some(-half_pi)
+	} else {
+		None
+	}
+}
+
+/// Returns e raised to the power of a [`struct@d128`]
+pub fn exp(input: d128) -> d128 {
+	// d128::exp never returns, so use d128::pow instead
+	d128!(2.718281828459045235360287471352662).pow(input)
+}
+
+/// Returns the hyperbolic sine of a [`struct@d128`]
+pub fn sinh(input: d128) -> d128 {
+	(exp(input) - exp(-input)) / d128!(2)
+}
+
+/// Returns the hyperbolic cosine of a [`struct@d128`]
+pub fn cosh(input: d128) -> d128 {
+	(exp(input) + exp(-input)) / d128!(2)
+}
+
+/// Returns the hyperbolic tangent of a [`struct@d128`]
+pub fn tanh(input: d128) -> d128 {
+	if input.is_negative() {
+		return -tanh(-input);
+	}
+	// e^-2x can't overflow, unlike sinh(x) / cosh(x)
+	let one = d128!(1);
+	let exp = exp(d128!(-2) * input);
+	(one - exp) / (one + exp)
+}
+
+/// Returns the inverse hyperbolic sine of a [`struct@d128`]
+pub fn asinh(input: d128) -> d128 {
+	if input.is_negative() {
+		return -asinh(-input);
+	}
+	(input + sqrt(input * input + d128!(1))).ln()
+}
+
+/// Returns the inverse hyperbolic cosine of a [`struct@d128`] of at least 1
+pub fn acosh(input: d128) -> d128 {
+	(input + sqrt(input * input - d128!(1))).ln()
+}
+
+/// Returns the inverse hyperbolic tangent of a [`struct@d128`] between -1 and 1
+pub fn atanh(input: d128) -> d128 {
+	let one = d128!(1);
+	((one + input) / (one - input)).ln() / d128!(2)
+}
+
+/// Returns the value of a [`Number`] in radians, for use in trigonometric
+/// functions. Numbers without a unit are already in radians.
+fn to_radians(number: &Number) -> Result<d128, Error> {
+	match number.unit.category() {
+		UnitType::NoType => Ok(number.value),
+		UnitType::Angle => Ok(convert(number.clone(), Unit::Radian)?.value),
+		_ => Err(Error::UnexpectedUnit {
+			unit: number.unit,
+			span: None,
+		}),
+	}
+}
+
+/// Returns the units and exponents of an [`AstNode`] made only of units,
+/// `*`, `/`, parentheses and integer powers, like `kg*m/s^2`
+fn unit_expression(ast_node: &AstNode) -> Option<Vec<(Unit, i8)>> {
+	let child = |index: usize| ast_node.children.get(index);
+	match &ast_node.token {
+		Token::Unit(unit) if ast_node.children.is_empty() => Some(vec![(*unit, 1)]),
+		Token::Paren => unit_expression(child(0)?),
+		Token::Operator(Multiply) | Token::Operator(Divide) => {
+			let mut units = unit_expression(child(0)?)?;
+			let right_units = unit_expression(child(1)?)?;
+			let sign = if ast_node.token == Token::Operator(Divide) {
+				-1
+			} else {
+				1
+			};
+			for (unit, exponent) in right_units {
+				units.push((unit, exponent.checked_mul(sign)?));
+			}
+			Some(units)
+		}
+		Token::Operator(Caret) => {
+			let units = unit_expression(child(0)?)?;
+			let exponent_node = child(1)?;
+			let exponent = match (&exponent_node.token, exponent_node.children.first()) {
+				(Token::Number(value), _) => to_integer(*value)?,
+				(
+					Token::Negative,
+					Some(AstNode {
+						token: Token::Number(value),
+						..
+					}),
+				) => -to_integer(*value)?,
+				_ => return None,
+			};
+			let exponent = i8::try_from(exponent).ok()?;
+			units
+				.into_iter()
+				.map(|(unit, unit_exponent)| Some((unit, unit_exponent.checked_mul(exponent)?)))
+				.collect()
+		}
+		_ => None,
+	}
+}
+
+/// Returns the units of an [`AstNode`] made only of units added together,
+/// like `ft+in` or `h:min:s`
+fn mixed_unit_expression(ast_node: &AstNode) -> Option<Vec<Unit>> {
+	match &ast_node.token {
+		Token::Unit(unit) if ast_node.children.is_empty() => Some(vec![*unit]),
+		Token::Operator(Plus) => {
+			let mut units = mixed_unit_expression(ast_node.children.first()?)?;
+			units.extend(mixed_unit_expression(ast_node.children.get(1)?)?);
+			Some(units)
+		}
+		_ => None,
+	}
+}
+
+/// Get a child of an [`AstNode`], or an [`Error::MalformedAst`] if it's missing
+fn get_child(ast_node: &AstNode, index: usize) -> Result<&AstNode, Error> {
+	ast_node
+		.children
+		.get(index)
+		.ok_or_else(|| Error::MalformedAst {
+			token: ast_node.token.clone(),
+			span: None,
+		})
+}
+
+/// Returns an [`Error::UnexpectedUnit`] unless the [`Number`] is [`UnitType::NoType`]
+fn expect_no_unit(number: &Number) -> Result<(), Error> {
+	if number.unit.category() == UnitType::NoType {
+		Ok(())
+	} else {
+		Err(Error::UnexpectedUnit {
+			unit: number.unit,
+			span: None,
+		})
+	}
+}
+
 /// Evaluate an [`AstNode`] into a [`Number`]
-fn evaluate_node(ast_node: &AstNode) -> Result<Number, String> {
+///
+/// Errors that don't have a [`Span`](crate::Span) yet get the span of the
+/// innermost [`AstNode`] they came from.
+fn evaluate_node(ast_node: &AstNode, context: &mut Context) -> Result<Number, Error> {
+	evaluate_node_inner(ast_node, context).map_err(|e| e.with_span(ast_node.span))
+}
+
+fn evaluate_node_inner(ast_node: &AstNode, context: &mut Context) -> Result<Number, Error> {
 	let token = &ast_node.token;
-	let children = &ast_node.children;
 	match token {
 		Token::Number(number) => Ok(Number::new(*number, Unit::NoUnit)),
 		Token::Constant(constant) => match constant {
@@ -101,50 +351,192 @@ fn evaluate_node(ast_node: &AstNode) -> Result<Number, String> {
 				d128!(2.718281828459045235360287471352662),
 				Unit::NoUnit,
 			)),
+			Now => Ok(Number::new(datetime::now(context.now), Unit::DateTime)),
+			Today => Ok(Number::new(datetime::today(context.now), Unit::Date)),
 		},
+		Token::Ans => context.ans.clone().ok_or_else(|| Error::UndefinedVariable {
+			name: "ans".to_string(),
+			span: None,
+		}),
+		Token::Identifier(name) => {
+			context
+				.variables
+				.get(name)
+				.cloned()
+				.ok_or_else(|| Error::UndefinedVariable {
+					name: name.clone(),
+					span: None,
+				})
+		}
+		Token::Assign => {
+			let identifier_node = get_child(ast_node, 0)?;
+			let value = evaluate_node(get_child(ast_node, 1)?, context)?;
+			if let Token::Identifier(name) = &identifier_node.token {
+				context.variables.insert(name.clone(), value.clone());
+				Ok(value)
+			} else {
+				Err(Error::UnexpectedToken {
+					token: Some(identifier_node.token.clone()),
+					expected: "variable name",
+					span: Some(identifier_node.span),
+				})
+			}
+		}
 		Token::FunctionIdentifier(function) => {
-			let child_node = children.get(0).ok_or("Paren has no child[0]")?;
-			let child_answer = evaluate_node(child_node)?;
+			let (min_args, max_args) = function.arity();
+			let found = ast_node.children.len();
+			if found < min_args || max_args.is_some_and(|max_args| found > max_args) {
+				return Err(Error::WrongArgumentCount {
+					function: function.clone(),
+					found,
+					span: None,
+				});
+			}
+			let mut args = Vec::with_capacity(found);
+			for child_node in &ast_node.children {
+				args.push(evaluate_node(child_node, context)?);
+			}
+			let child_answer = args[0].clone();
+			let domain_error = || Error::DomainError {
+				function: function.clone(),
+				value: child_answer.value,
+				span: None,
+			};
 			match function {
 				Cbrt => {
-					if child_answer.unit.category() == UnitType::NoType {
-						let result = cbrt(child_answer.value);
-						Ok(Number::new(result, child_answer.unit))
+					if child_answer.unit == Unit::NoUnit {
+						Ok(Number::new(cbrt(child_answer.value), Unit::NoUnit))
 					} else {
-						Err("log() only accepts UnitType::NoType".to_string())
+						// cbrt(8 l), cbrt(-27 m3)
+						units::root(child_answer, 3)
 					}
 				}
 				Sqrt => {
-					if child_answer.unit.category() == UnitType::NoType {
-						let result = sqrt(child_answer.value);
-						Ok(Number::new(result, child_answer.unit))
+					if child_answer.value.is_negative() {
+						return Err(domain_error());
+					}
+					if child_answer.unit == Unit::NoUnit {
+						Ok(Number::new(sqrt(child_answer.value), Unit::NoUnit))
 					} else {
-						Err("log() only accepts UnitType::NoType".to_string())
+						// sqrt(4 m2), sqrt(100 W * 4 ohm)
+						units::root(child_answer, 2)
 					}
 				}
 				Log => {
-					if child_answer.unit.category() == UnitType::NoType {
-						let result = child_answer.value.log10();
-						Ok(Number::new(result, child_answer.unit))
-					} else {
-						Err("log() only accepts UnitType::NoType".to_string())
+					expect_no_unit(&child_answer)?;
+					if child_answer.value <= d128!(0) {
+						return Err(domain_error());
 					}
+					let result = match args.get(1) {
+						Some(base) => {
+							expect_no_unit(base)?;
+							if base.value <= d128!(0) || base.value == d128!(1) {
+								return Err(Error::DomainError {
+									function: function.clone(),
+									value: base.value,
+									span: None,
+								});
+							}
+							child_answer.value.ln() / base.value.ln()
+						}
+						None => child_answer.value.log10(),
+					};
+					Ok(Number::new(result, child_answer.unit))
 				}
 				Ln => {
-					if child_answer.unit.category() == UnitType::NoType {
-						let result = child_answer.value.ln();
-						Ok(Number::new(result, child_answer.unit))
-					} else {
-						Err("ln() only accepts UnitType::NoType".to_string())
+					expect_no_unit(&child_answer)?;
+					if child_answer.value <= d128!(0) {
+						return Err(domain_error());
 					}
+					let result = child_answer.value.ln();
+					Ok(Number::new(result, child_answer.unit))
 				}
 				Exp => {
-					if child_answer.unit.category() == UnitType::NoType {
-						let result = child_answer.value.exp(child_answer.value);
+					expect_no_unit(&child_answer)?;
+					let result = exp(child_answer.value);
+					Ok(Number::new(result, child_answer.unit))
+				}
+				Root => {
+					let degree = &args[1];
+					expect_no_unit(degree)?;
+					let odd_integer =
+						to_integer(degree.value).is_some_and(|degree| degree % 2 != 0);
+					if degree.value.is_zero() {
+						return Err(Error::DomainError {
+							function: function.clone(),
+							value: degree.value,
+							span: None,
+						});
+					}
+					if child_answer.value.is_negative() && !odd_integer {
+						return Err(domain_error());
+					}
+					if child_answer.unit != Unit::NoUnit {
+						// root(16 m4, 4)
+						let integer_degree =
+							to_integer(degree.value).and_then(|d| i8::try_from(d).ok());
+						return match integer_degree {
+							Some(integer_degree) => units::root(child_answer, integer_degree),
+							None => Err(Error::FractionalDimensions {
+								unit: child_answer.unit,
+								exponent: d128!(1) / degree.value,
+								span: None,
+							}),
+						};
+					}
+					let result = root(child_answer.value.abs(), degree.value);
+					if child_answer.value.is_negative() {
+						Ok(Number::new(-result, child_answer.unit))
+					} else {
 						Ok(Number::new(result, child_answer.unit))
+					}
+				}
+				Min | Max => {
+					// compare in the unit of the current result, but return the
+					// argument in its own unit
+					let mut result = child_answer.clone();
+					for arg in &args[1..] {
+						let converted = convert(arg.clone(), result.unit)?;
+						let replace = match function {
+							Min => converted.value < result.value,
+							_ => converted.value > result.value,
+						};
+						if replace {
+							result = arg.clone();
+						}
+					}
+					Ok(result)
+				}
+				Clamp => {
+					let low = convert(args[1].clone(), child_answer.unit)?.value;
+					let high = convert(args[2].clone(), child_answer.unit)?.value;
+					if low > high {
+						return Err(Error::DomainError {
+							function: function.clone(),
+							value: low,
+							span: None,
+						});
+					}
+					let result = if child_answer.value < low {
+						low
+					} else if child_answer.value > high {
+						high
 					} else {
-						Err("exp() only accepts UnitType::NoType".to_string())
+						child_answer.value
+					};
+					Ok(Number::new(result, child_answer.unit))
+				}
+				Hypot => {
+					let unit = child_answer.unit;
+					if unit.category() == UnitType::Temperature {
+						return Err(Error::UnexpectedUnit { unit, span: None });
 					}
+					let mut sum = d128!(0);
+					for arg in &args {
+						let value = convert(arg.clone(), unit)?.value;
+						sum += value * value;
+					}
+					Ok(Number::new(sqrt(sum), unit))
 				}
 				Round => {
 					// .quantize() rounds .5 to nearest even integer, so we correct that
@@ -181,38 +573,146 @@ fn evaluate_node(ast_node: &AstNode) -> Result<Number, String> {
 					Ok(Number::new(result, child_answer.unit))
 				}
 				Sin => {
-					let result = sin(child_answer.value);
-					Ok(Number::new(result, child_answer.unit))
+					let result = sin(to_radians(&child_answer)?);
+					Ok(Number::new(result, Unit::NoUnit))
 				}
 				Cos => {
-					let result = cos(child_answer.value);
-					Ok(Number::new(result, child_answer.unit))
+					let result = cos(to_radians(&child_answer)?);
+					Ok(Number::new(result, Unit::NoUnit))
 				}
 				Tan => {
-					let result = tan(child_answer.value);
+					let radians = to_radians(&child_answer)?;
+					let cos = cos(radians);
+					if is_trig_zero(cos, radians) {
+						return Err(domain_error());
+					}
+					Ok(Number::new(sin(radians) / cos, Unit::NoUnit))
+				}
+				Sec => {
+					let radians = to_radians(&child_answer)?;
+					let cos = cos(radians);
+					if is_trig_zero(cos, radians) {
+						return Err(domain_error());
+					}
+					Ok(Number::new(d128!(1) / cos, Unit::NoUnit))
+				}
+				Csc => {
+					let radians = to_radians(&child_answer)?;
+					let sin = sin(radians);
+					if is_trig_zero(sin, radians) {
+						return Err(domain_error());
+					}
+					Ok(Number::new(d128!(1) / sin, Unit::NoUnit))
+				}
+				Cot => {
+					let radians = to_radians(&child_answer)?;
+					let sin = sin(radians);
+					if is_trig_zero(sin, radians) {
+						return Err(domain_error());
+					}
+					Ok(Number::new(cos(radians) / sin, Unit::NoUnit))
+				}
+				Asin => {
+					expect_no_unit(&child_answer)?;
+					if child_answer.value.abs() > d128!(1) {
+						return Err(domain_error());
+					}
+					let result = asin(child_answer.value);
+					Ok(Number::new(result, Unit::Radian))
+				}
+				Acos => {
+					expect_no_unit(&child_answer)?;
+					if child_answer.value.abs() > d128!(1) {
+						return Err(domain_error());
+					}
+					let result = acos(child_answer.value);
+					Ok(Number::new(result, Unit::Radian))
+				}
+				Atan => {
+					expect_no_unit(&child_answer)?;
+					let result = atan(child_answer.value);
+					Ok(Number::new(result, Unit::Radian))
+				}
+				Atan2 => {
+					let (y, x) = convert_to_lowest(child_answer, args[1].clone())?;
+					if y.unit.category() == UnitType::Temperature {
+						return Err(Error::UnexpectedUnit {
+							unit: y.unit,
+							span: None,
+						});
+					}
+					match atan2(y.value, x.value) {
+						Some(result) => Ok(Number::new(result, Unit::Radian)),
+						None => Err(Error::DomainError {
+							function: function.clone(),
+							value: y.value,
+							span: None,
+						}),
+					}
+				}
+				Sinh => {
+					expect_no_unit(&child_answer)?;
+					let result = sinh(child_answer.value);
+					Ok(Number::new(result, child_answer.unit))
+				}
+				Cosh => {
+					expect_no_unit(&child_answer)?;
+					let result = cosh(child_answer.value);
+					Ok(Number::new(result, child_answer.unit))
+				}
+				Tanh => {
+					expect_no_unit(&child_answer)?;
+					let result = tanh(child_answer.value);
+					Ok(Number::new(result, child_answer.unit))
+				}
+				Asinh => {
+					expect_no_unit(&child_answer)?;
+					let result = asinh(child_answer.value);
+					Ok(Number::new(result, child_answer.unit))
+				}
+				Acosh => {
+					expect_no_unit(&child_answer)?;
+					if child_answer.value < d128!(1) {
+						return Err(domain_error());
+					}
+					let result = acosh(child_answer.value);
+					Ok(Number::new(result, child_answer.unit))
+				}
+				Atanh => {
+					expect_no_unit(&child_answer)?;
+					if child_answer.value.abs() >= d128!(1) {
+						return Err(domain_error());
+					}
+					let result = atanh(child_answer.value);
 					Ok(Number::new(result, child_answer.unit))
 				}
 			}
 		}
-		Token::Unit(unit) => {
-			let child_node = children.get(0).ok_or("Unit has no child[0]")?;
-			let child_answer = evaluate_node(child_node)?;
-			Ok(Number::new(child_answer.value, *unit))
+		// a date or time of day in a time zone, like "3pm PST"
+		Token::Unit(Unit::Zoned(zone)) if !ast_node.children.is_empty() => {
+			let child_answer = evaluate_node(get_child(ast_node, 0)?, context)?;
+			datetime::in_time_zone(child_answer, *zone, context.now)
 		}
+		Token::Unit(unit) => match ast_node.children.first() {
+			Some(child_node) => {
+				let child_answer = evaluate_node(child_node, context)?;
+				Ok(Number::new(child_answer.value, *unit))
+			}
+			// a unit without a value, like the m and s in "9.81 kg*m/s^2"
+			None => Ok(Number::new(d128!(1), *unit)),
+		},
 		Token::Negative => {
-			let child_node = children.get(0).ok_or("Negative has no child[0]")?;
-			let child_answer = evaluate_node(child_node)?;
+			let child_node = get_child(ast_node, 0)?;
+			let child_answer = evaluate_node(child_node, context)?;
 			Ok(Number::new(-child_answer.value, child_answer.unit))
 		}
 		Token::Paren => {
-			let child_node = children.get(0).ok_or("Paren has no child[0]")?;
-			evaluate_node(child_node)
+			let child_node = get_child(ast_node, 0)?;
+			evaluate_node(child_node, context)
 		}
 		Token::UnaryOperator(operator) => {
-			let child_node = children
-				.get(0)
-				.ok_or(format!("Token {:?} has no child[0]", token))?;
-			let child_answer = evaluate_node(child_node)?;
+			let child_node = get_child(ast_node, 0)?;
+			let child_answer = evaluate_node(child_node, context)?;
 			match operator {
 				Percent => Ok(Number::new(
 					child_answer.value / d128!(100),
@@ -221,67 +721,105 @@ fn evaluate_node(ast_node: &AstNode) -> Result<Number, String> {
 				Factorial => {
 					let result = factorial(child_answer.value);
 					if result.is_nan() {
-						return Err(
-							"Can only perform factorial on integers from 0 to 1000".to_string()
-						);
+						return Err(Error::FactorialOutOfRange {
+							value: child_answer.value,
+							span: None,
+						});
 					}
 					Ok(Number::new(result, child_answer.unit))
 				}
 			}
 		}
 		Token::NamedNumber(named_number) => {
-			let child_node = children
-				.get(0)
-				.ok_or(format!("Token {:?} has no child[0]", token))?;
+			let child_node = get_child(ast_node, 0)?;
 			let named_number_value = lookup_named_number(named_number);
 			if let Token::NamedNumber(child_nn) = &child_node.token {
 				let child_nn_value = lookup_named_number(child_nn);
 				if child_nn_value > named_number_value {
-					return Err(format!("Unexpected smaller token {:?}", token));
+					return Err(Error::NamedNumberOrder { span: None });
 				}
 			}
-			let child_answer = evaluate_node(child_node)?;
+			let child_answer = evaluate_node(child_node, context)?;
 			let result = child_answer.value * named_number_value;
 			Ok(Number::new(result, child_answer.unit))
 		}
 		Token::TextOperator(operator) => {
-			let left_child = children
-				.get(0)
-				.ok_or(format!("Token {:?} has no child[0]", token))?;
-			let right_child = children
-				.get(1)
-				.ok_or(format!("Token {:?} has no child[1]", token))?;
+			let left_child = get_child(ast_node, 0)?;
+			let right_child = get_child(ast_node, 1)?;
 
 			match operator {
-				To => {
-					if let Token::Unit(right_unit) = right_child.token {
-						let left = evaluate_node(left_child)?;
+				To => match right_child.token {
+					Token::Unit(right_unit) if right_child.children.is_empty() => {
+						let left = evaluate_node(left_child, context)?;
 						let result = convert(left, right_unit)?;
 						Ok(result)
-					} else {
-						Err("Right side of To operator needs to be a unit".to_string())
 					}
-				}
+					Token::NumberFormat(format) => {
+						let left = evaluate_node(left_child, context)?;
+						if let NumberFormat::Base(base) = format {
+							match to_integer(left.value) {
+								Some(integer) if integer >= 0 => {}
+								_ => {
+									return Err(Error::InvalidBaseConversion {
+										value: left.value,
+										base,
+										span: None,
+									})
+								}
+							}
+						}
+						if format == NumberFormat::Clock && left.unit.category() != UnitType::Time {
+							return Err(Error::CannotConvert {
+								from: left.unit,
+								to: Unit::Second,
+								span: None,
+							});
+						}
+						Ok(Number { format, ..left })
+					}
+					_ => {
+						// mixed units, like "ft+in"
+						if let Some(units) = mixed_unit_expression(right_child) {
+							let mixed = MixedUnits::new(&units)?;
+							let left = evaluate_node(left_child, context)?;
+							let result = convert(left, *units.last().unwrap())?;
+							return Ok(Number {
+								format: NumberFormat::Mixed(mixed),
+								..result
+							});
+						}
+						// a unit expression, like "kg*m/s^2"
+						let right_unit = unit_expression(right_child)
+							.and_then(|units| composite_unit(&units))
+							.ok_or(Error::ExpectedUnit { span: None })?;
+						let left = evaluate_node(left_child, context)?;
+						convert(left, right_unit)
+					}
+				},
 				Of => {
-					let left = evaluate_node(left_child)?;
-					let right = evaluate_node(right_child)?;
+					let left = evaluate_node(left_child, context)?;
+					let right = evaluate_node(right_child, context)?;
 					if left.unit == Unit::NoUnit {
 						Ok(Number::new(left.value * right.value, right.unit))
 					} else {
-						Err("Left side of the Of operator must be NoUnit".to_string())
+						Err(Error::UnexpectedUnit {
+							unit: left.unit,
+							span: None,
+						})
 					}
 				}
 			}
 		}
+		Token::Operator(BitNot) => {
+			let child_node = get_child(ast_node, 0)?;
+			let child_answer = evaluate_node(child_node, context)?;
+			bitwise_not(child_answer)
+		}
 		Token::Operator(operator) => {
-			let left_child = children
-				.get(0)
-				.ok_or(format!("Token {:?} has no child[0]", token))?;
-			let right_child = children
-				.get(1)
-				.ok_or(format!("Token {:?} has no child[1]", token))?;
-			let left = evaluate_node(left_child)?;
-			let right = evaluate_node(right_child)?;
+			let left_child = get_child(ast_node, 0)?;
+			let right_child = get_child(ast_node, 1)?;
+			let left = evaluate_node(left_child, context)?;
+			let right = evaluate_node(right_child, context)?;
 			match operator {
 				Plus => Ok(add(left, right)?),
 				Minus => Ok(subtract(left, right)?),
@@ -289,9 +827,307 @@ fn evaluate_node(ast_node: &AstNode) -> Result<Number, String> {
 				Divide => Ok(divide(left, right)?),
 				Modulo => Ok(modulo(left, right)?),
 				Caret => Ok(pow(left, right)?),
-				_ => Err(format!("Unexpected operator {:?}", operator)),
+				BitAnd | BitOr | BitXor | ShiftLeft | ShiftRight => {
+					bitwise(left, right, operator.clone())
+				}
+				_ => Err(Error::MalformedAst {
+					token: token.clone(),
+					span: None,
+				}),
 			}
 		}
-		_ => Err(format!("Unexpected token {:?}", token)),
+		_ => Err(Error::UnexpectedToken {
+			token: Some(token.clone()),
+			expected: "a number, unit or operator",
+			span: None,
+		}),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::dimensions::Dimensions;
+	use crate::units::Unit;
+	use crate::{eval, eval_with_context, Context, Error, Span};
+	use decimal::d128;
+
+	fn eval_ok(input: &str) -> String {
+		eval(input, true, Unit::Celsius, false).unwrap().to_string()
+	}
+
+	fn eval_err(input: &str) -> Error {
+		eval(input, true, Unit::Celsius, false).unwrap_err()
+	}
+
+	#[test]
+	fn test_variables() {
+		let mut context = Context::default();
+		let mut eval = |input| eval_with_context(input, &mut context, true, Unit::Celsius, false);
+		assert_eq!(eval("x = 5 km").unwrap().to_string(), "5 Kilometer");
+		assert_eq!(eval("2x").unwrap().to_string(), "10 Kilometer");
+		assert_eq!(
+			eval("x / 20 min").unwrap().to_string(),
+			"15 KilometersPerHour"
+		);
+		// time zone names and abbreviations can be variable names
+		assert_eq!(eval("paris = 3").unwrap().to_string(), "3");
+		assert_eq!(eval("est = 2").unwrap().to_string(), "2");
+		assert_eq!(eval("paris * est").unwrap().to_string(), "6");
+		assert!(matches!(
+			eval("3pm est to utc").unwrap().unit,
+			Unit::Zoned(_)
+		));
+
+		assert!(matches!(eval_err("1 kohm"), Error::UnknownWord { word, .. } if word == "kohm"));
+		assert!(matches!(eval_err("1 foo"), Error::UnknownWord { word, .. } if word == "foo"));
+		assert!(
+			matches!(eval_err("foo * 2"), Error::UndefinedVariable { name, .. } if name == "foo")
+		);
+		assert!(matches!(eval_err("m = 5"), Error::ReservedName { .. }));
+	}
+
+	#[test]
+	fn test_e_notation() {
+		assert_eq!(eval_ok("6.022E23 * 2"), "1204400000000000000000000");
+		assert_eq!(eval_ok("1E-3 m to mm"), "1.000 Millimeter");
+		assert_eq!(eval_ok("3e8 m/s to km/h"), "1080000000 KilometersPerHour");
+		assert_eq!(eval_ok("2e+10"), "20000000000");
+		assert_eq!(eval_ok("1.6E-19 C"), "1.6E-19 Coulomb");
+		assert_eq!(eval_ok("2e"), eval_ok("2 * e"));
+		assert_eq!(eval_ok("2 e10"), eval_ok("2 * e * 10"));
+	}
+
+	#[test]
+	fn test_bitwise() {
+		assert_eq!(eval_ok("12 and 10"), "8");
+		assert_eq!(eval_ok("12 or 3"), "15");
+		assert_eq!(eval_ok("12 xor 10"), "6");
+		assert_eq!(eval_ok("not 0"), "-1");
+		assert_eq!(eval_ok("1 << 4"), "16");
+		assert_eq!(eval_ok("-8 >> 1"), "-4");
+		assert_eq!(eval_ok("1 KiB << 10"), "1024 Kibibyte");
+		assert_eq!(eval_ok("0b1010 xor 0b0110 to bin"), "0b1100");
+
+		// shifts bind tighter than and, which binds tighter than xor and or
+		assert_eq!(eval_ok("4 or 1 and 0"), "4");
+		assert_eq!(eval_ok("1 or 1 << 4"), "17");
+		assert_eq!(eval_ok("6 xor 3 and 1"), "7");
+		assert_eq!(eval_ok("4 or 6 xor 2"), "4");
+		assert_eq!(eval_ok("1 << 2 << 3"), "32");
+		assert_eq!(eval_ok("5 - 1 << 1"), "8");
+
+		assert!(matches!(eval_err("1.5 and 1"), Error::NotAnInteger { .. }));
+		assert!(matches!(eval_err("1 << -1"), Error::NegativeShift { .. }));
+	}
+
+	#[test]
+	fn test_trigonometry() {
+		assert_eq!(eval_ok("csc(30 deg)"), "2");
+		assert_eq!(eval_ok("csc(1e-20)"), "100000000000000000000");
+		assert_eq!(eval_ok("tanh(0)"), "0");
+
+		// values at known points, to within the last few digits
+		let values = [
+			("atan(1)", d128!(0.7853981633974483096156608458198757)),
+			("atan(-1)", d128!(-0.7853981633974483096156608458198757)),
+			("atan(100)", d128!(1.560796660108231381024981575430472)),
+			("asin(0.5)", d128!(0.5235987755982988730771072305465838)),
+			("asin(1)", d128!(1.570796326794896619231321691639751)),
+			("acos(0.5)", d128!(1.047197551196597746154214461093168)),
+			("acos(-1)", d128!(3.141592653589793238462643383279503)),
+			("atan2(1, 1)", d128!(0.7853981633974483096156608458198757)),
+			("atan2(-1, -1)", d128!(-2.356194490192344928846982537459627)),
+			("sinh(1)", d128!(1.175201193643801456882381850595601)),
+			("cosh(1)", d128!(1.543080634815243778477905620757062)),
+			("tanh(1)", d128!(0.7615941559557648881194582826047936)),
+			("asinh(1)", d128!(0.8813735870195430252326093249797923)),
+			("acosh(1)", d128!(0)),
+			("acosh(2)", d128!(1.316957896924816708625046347307968)),
+			("atanh(0.5)", d128!(0.5493061443340548456976226184612628)),
+		];
+		for (input, expected) in values {
+			let value = eval(input, true, Unit::Celsius, false).unwrap().value;
+			assert!(
+				(value - expected).abs() < d128!(1E-30),
+				"{} is {}",
+				input,
+				value
+			);
+		}
+
+		for input in [
+			"tan(90 deg)",
+			"tan(-270 deg)",
+			"tan(pi/2)",
+			"sec(90 deg)",
+			"sec(-90 deg)",
+			"csc(0)",
+			"csc(360 deg)",
+			"cot(180 deg)",
+			"asin(2)",
+			"acosh(0.5)",
+			"atan2(0, 0)",
+		] {
+			assert!(
+				matches!(eval_err(input), Error::DomainError { .. }),
+				"{}",
+				input
+			);
+		}
+	}
+
+	#[test]
+	fn test_functions() {
+		assert_eq!(eval_ok("max(3 km, 2 mi)"), "2 Mile");
+		assert_eq!(eval_ok("min(3 km, 2 mi)"), "3 Kilometer");
+		assert_eq!(eval_ok("max(1, 2, 3)"), "3");
+		assert_eq!(eval_ok("min (3, 4)"), "3");
+		// commas in function calls separate arguments
+		assert_eq!(eval_ok("max(1,500)"), "500");
+		assert_eq!(eval_ok("max((1,500), 2)"), "1500");
+		assert_eq!(eval_ok("1,000,000 + 1"), "1000001");
+		// a comma followed by 3 digits is ambiguous if other commas have a space after them
+		assert!(matches!(
+			eval_err("max(1, 2,000)"),
+			Error::AmbiguousComma { .. }
+		));
+		assert!(matches!(eval_err("1,5"), Error::InvalidNumber { .. }));
+		assert_eq!(eval_ok("clamp(15, 0, 10)"), "10");
+		assert_eq!(eval_ok("clamp(-5 m, 0 m, 10 m)"), "0 Meter");
+		assert_eq!(
+			eval_ok("hypot(3 m, 4 m)"),
+			"5.000000000000000000000000000000000 Meter"
+		);
+		assert_eq!(eval_ok("log(100)"), "2");
+		assert_eq!(eval_ok("log(8, 2)"), "3.000000000000000000000000000000000");
+		assert_eq!(
+			eval_ok("root(27, 3)"),
+			"3.000000000000000000000000000000000"
+		);
+		assert_eq!(eval_ok("5 min"), "5 Minute");
+
+		assert!(matches!(
+			eval_err("max(1 kg, 1 m)"),
+			Error::CannotConvert { .. }
+		));
+		assert!(matches!(
+			eval_err("clamp(5, 10, 0)"),
+			Error::DomainError { .. }
+		));
+		assert!(matches!(eval_err("log(8, 1)"), Error::DomainError { .. }));
+		assert!(matches!(
+			eval_err("atan2(1)"),
+			Error::WrongArgumentCount { found: 1, .. }
+		));
+		assert!(matches!(
+			eval_err("sqrt(1,000,000)"),
+			Error::WrongArgumentCount { found: 3, .. }
+		));
+	}
+
+	#[test]
+	fn test_dimension_overflow() {
+		assert_eq!(eval_ok("(2 m)^2 * 3 m"), "12 CubicMeter");
+		assert_eq!(
+			eval_err("(2 m)^100 * (2 m)^100"),
+			Error::DimensionOverflow {
+				span: Some(Span::new(0, 21))
+			}
+		);
+		assert!(matches!(
+			eval_err("(1 m)^-128 / 1 m"),
+			Error::DimensionOverflow { .. }
+		));
+		assert!(matches!(
+			eval_err("1 m^100 * 1 km^100"),
+			Error::DimensionOverflow { .. }
+		));
+		// -128 is a valid exponent, even though 128 isn't
+		let dimensions = Dimensions::LENGTH.checked_pow(-128).unwrap();
+		assert_eq!(dimensions.to_string(), "1/m¹²⁸");
+		let dimensions = Dimensions::TIME.checked_mul(dimensions).unwrap();
+		assert_eq!(dimensions.to_string(), "s/m¹²⁸");
+		assert!(Dimensions::TIME.checked_div(dimensions).is_err());
+	}
+
+	#[test]
+	fn test_unit_powers() {
+		assert_eq!(eval_ok("2 m^2"), "2 SquareMeter");
+		assert_eq!(eval_ok("2 m²"), "2 SquareMeter");
+		assert_eq!(eval_ok("2 s^-1"), "2 Hertz");
+		assert_eq!(eval_ok("2 kg*m^2"), "2 Kilogram·Meter²");
+		assert_eq!(eval_ok("2 m^2*kg"), "2 Kilogram·Meter²");
+		assert_eq!(eval_ok("2 m/s^2"), "2 MetersPerSecondSquared");
+		assert_eq!(eval_ok("(2 m)^2"), "4 SquareMeter");
+		assert_eq!(eval_ok("(1 m)^-128"), "1 1/Meter¹²⁸");
+		assert_eq!(eval_ok("1 kg^-128"), "1 1/Kilogram¹²⁸");
+		assert_eq!(eval_ok("1 m^-128 * 1 s"), "1 Second/Meter¹²⁸");
+	}
+
+	#[test]
+	fn test_fractional_powers() {
+		assert_eq!(eval_ok("(2 m/s)^2"), "4 Meter²/Second²");
+		assert_eq!(
+			eval_ok("(9 m2)^0.5"),
+			"3.000000000000000000000000000000000 Meter"
+		);
+		assert_eq!(
+			eval_ok("(-8 m3)^(1/3)"),
+			"-2.000000000000000000000000000000000 Meter"
+		);
+		assert_eq!(eval_ok("(-2 m)^2"), "4 SquareMeter");
+
+		assert!(matches!(
+			eval_err("(4 m2)^0.25"),
+			Error::FractionalDimensions {
+				unit: Unit::SquareMeter,
+				..
+			}
+		));
+		assert!(matches!(
+			eval_err("sqrt(4 m)"),
+			Error::FractionalDimensions {
+				unit: Unit::Meter,
+				..
+			}
+		));
+		assert!(matches!(
+			eval_err("cbrt(8 m2)"),
+			Error::FractionalDimensions { .. }
+		));
+		assert!(matches!(
+			eval_err("root(16 m2, 2.5)"),
+			Error::FractionalDimensions { .. }
+		));
+		assert_eq!(
+			eval_ok("cbrt(-8 m3)"),
+			"-2.000000000000000000000000000000000 Meter"
+		);
+		assert!(matches!(eval_err("(-4 m2)^0.5"), Error::DomainError { .. }));
+		assert!(matches!(
+			eval_err("(1 m)^200"),
+			Error::DimensionOverflow { .. }
+		));
+	}
+
+	#[test]
+	fn test_newton_meters() {
+		assert_eq!(eval_ok("1 Nm to J"), "1 Joule");
+		assert_eq!(eval_ok("1 Nm"), "1 NewtonMeter");
+		assert_eq!(eval_ok("500 nm to µm"), "0.500 Micrometer");
+		assert!(matches!(eval_err("1 nm to J"), Error::CannotConvert { .. }));
+	}
+
+	#[test]
+	fn test_mixed_unit_targets() {
+		assert_eq!(eval_ok("200 lb to st+lb"), "14 Stone 4 Pound");
+		assert_eq!(eval_ok("13.5 st to st+lb"), "13 Stone 7 Pound");
+		assert_eq!(eval_ok("2 st to lb"), "4000 Pound");
+		assert_eq!(eval_ok("13 st 7 lb to lb"), "189 Pound");
+		assert_eq!(eval_ok("(1/3) h to h:min:s"), "0 Hour 20 Minute 0 Second");
+		assert!(matches!(
+			eval_err("1 m to in+ft"),
+			Error::InvalidMixedUnits { .. }
+		));
 	}
 }
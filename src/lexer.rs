@@ -1,14 +1,17 @@
-use std::iter::Peekable;
 use std::str::FromStr;
 use decimal::d128;
-use crate::Token;
-use crate::Operator::{Caret, Divide, LeftParen, Minus, Modulo, Multiply, Plus, RightParen};
+use crate::{to_integer, Error, NumberFormat, Span, SpannedToken, Token};
+use crate::currency::Currency;
+use crate::datetime;
+use crate::timezone::TimeZone;
+use crate::Operator::{BitAnd, BitNot, BitOr, BitXor, Caret, Comma, Divide, LeftParen, Minus, Modulo, Multiply, Plus, RightParen, ShiftLeft, ShiftRight};
 use crate::UnaryOperator::{Percent, Factorial};
 use crate::TextOperator::{Of, To};
 use crate::NamedNumber::*;
-use crate::Constant::{E, Pi};
-use crate::LexerKeyword::{In, PercentChar, Per, Mercury, Hg, PoundForce, Force, DoubleQuotes, Revolution};
-use crate::FunctionIdentifier::{Cbrt, Ceil, Cos, Exp, Abs, Floor, Ln, Log, Round, Sin, Sqrt, Tan};
+use crate::Constant::{E, Pi, Now, Today};
+use crate::LexerKeyword::{In, PercentChar, Per, Mercury, Hg, Force, DoubleQuotes, Revolution, AmbiguousDegree};
+use crate::FunctionIdentifier::{Acos, Acosh, Asin, Asinh, Atan, Atan2, Atanh, Cbrt, Ceil, Cos, Cosh, Cot, Csc, Exp, Abs, Floor, Ln, Log, Round, Root, Min, Max, Clamp, Hypot, Sec, Sin, Sinh, Sqrt, Tan, Tanh};
+use crate::units::UnitType;
 use crate::units::Unit;
 use crate::units::Unit::*;
 use unicode_segmentation::{Graphemes, UnicodeSegmentation};
@@ -21,7 +24,7 @@ fn is_word_char_str(input: &str) -> bool {
 		"a" | "b" | "c" | "d" | "e" | "f" | "g" | "h" | "i" | "j" | "k" | "l"
 		| "m" | "n" | "o" | "p" | "q" | "r" | "s" | "t" | "u" | "v" | "w" | "x"
 		| "y" | "z" => true,
-		"Ω" | "Ω" | "µ" | "μ" => true,
+		"Ω" | "Ω" | "µ" | "μ" | "Å" | "Å" | "Å" | "å" => true,
 		_ => false,
 	}
 }
@@ -30,9 +33,13 @@ fn is_numeric_str(input: &str) -> bool {
 	matches!(input, "." | "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9")
 }
 
+fn is_digit_str(input: &str) -> bool {
+	matches!(input, "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9")
+}
+
 /// Read next characters as a word, otherwise return empty string.
 /// Returns an empty string if there's leading whitespace.
-fn read_word_plain(chars: &mut Peekable<Graphemes>) -> String {
+fn read_word_plain(chars: &mut Chars) -> String {
 	let mut word = String::new();
 	while let Some(next_char) = chars.peek() {
 		if is_word_char_str(next_char) {
@@ -59,6 +66,7 @@ fn read_word(first_c: &str, lexer: &mut Lexer) -> String {
 			}
 		}
 	}
+	let word_start = chars.pos() - word.len();
 	while let Some(next_char) = chars.peek() {
 		if is_word_char_str(next_char) {
 			word += chars.next().unwrap();
@@ -67,7 +75,7 @@ fn read_word(first_c: &str, lexer: &mut Lexer) -> String {
 		}
 	}
 	if !word.is_empty() {
-		match *chars.peek().unwrap_or(&"") {
+		match chars.peek().unwrap_or("") {
 			"2" | "²" => {
 				word += "2";
 				chars.next();
@@ -79,73 +87,392 @@ fn read_word(first_c: &str, lexer: &mut Lexer) -> String {
 			_ => {},
 		}
 	}
+	lexer.word_start = word_start;
 	word
 }
 
-fn parse_token(c: &str, lexer: &mut Lexer) -> Result<(), String> {
-	let tokens = &mut lexer.tokens;
+/// Read E notation like the `e+10` in `2e+10` into `number_string`.
+///
+/// An `e` is only part of the number when it directly follows the digits and
+/// is followed by an integer exponent, optionally signed. Otherwise it's the
+/// constant [`E`], so `2e` and `2 e10` are still `2 * e`, but `2e-3` is `0.002`.
+fn read_exponent(number_string: &mut String, chars: &mut Chars) {
+	if chars.peek() != Some("e") {
+		return;
+	}
+	let sign_len = match chars.peek_nth(1) {
+		Some("+") | Some("-") => 1,
+		_ => 0,
+	};
+	if !matches!(chars.peek_nth(1 + sign_len), Some(digit) if is_digit_str(digit)) {
+		return;
+	}
+	for _ in 0..1 + sign_len {
+		*number_string += chars.next().unwrap();
+	}
+	while let Some(digit) = chars.peek() {
+		if is_digit_str(digit) {
+			*number_string += digit;
+			chars.next();
+		} else {
+			break;
+		}
+	}
+}
+
+fn is_digit_in_radix(input: &str, radix: u32) -> bool {
+	let mut chars = input.chars();
+	match (chars.next(), chars.next()) {
+		(Some(c), None) => c.is_digit(radix),
+		_ => false,
+	}
+}
+
+/// Returns the radix of a `0x`, `0o` or `0b` prefix, when the `0` was just read
+/// and the prefix is followed by a digit in that radix
+fn radix_prefix(chars: &Chars) -> Option<u32> {
+	let radix = match chars.peek() {
+		Some("x") => 16,
+		Some("o") => 8,
+		Some("b") => 2,
+		_ => return None,
+	};
+	match chars.peek_nth(1) {
+		Some(digit) if is_digit_in_radix(digit, radix) => Some(radix),
+		_ => None,
+	}
+}
+
+/// Returns true if the next characters match `pattern`, where `d` matches a
+/// digit and any other character matches itself
+fn next_matches(chars: &Chars, pattern: &str) -> bool {
+	pattern.chars().enumerate().all(|(i, expected)| match (expected, chars.peek_nth(i)) {
+		('d', Some(c)) => is_digit_str(c),
+		(expected, Some(c)) => c.len() == expected.len_utf8() && c.starts_with(expected),
+		(_, None) => false,
+	})
+}
+
+/// Read the rest of an ISO 8601 date, when the year was just read, like
+/// `-03-01`, `-03-01t12:00` or `-03-01 12:00:30.5z`. Pushes the seconds since
+/// the Unix epoch as a number, followed by a [`Date`] or [`DateTime`] unit.
+fn read_date(year: &str, lexer: &mut Lexer) -> Result<(), Error> {
+	let mut date = year.to_owned();
+	let mut take = |lexer: &mut Lexer, count: usize| {
+		for _ in 0..count {
+			date += lexer.chars.next().unwrap();
+		}
+	};
+	take(lexer, 6);
+	let mut unit = Date;
+	if next_matches(&lexer.chars, "tdd:dd") || next_matches(&lexer.chars, " dd:dd") {
+		unit = DateTime;
+		take(lexer, 6);
+		if next_matches(&lexer.chars, ":dd") {
+			take(lexer, 3);
+			if next_matches(&lexer.chars, ".d") {
+				take(lexer, 1);
+				while lexer.chars.peek().is_some_and(is_digit_str) {
+					take(lexer, 1);
+				}
+			}
+		}
+		if lexer.chars.peek() == Some("z") && !lexer.chars.peek_nth(1).is_some_and(is_word_char_str) {
+			take(lexer, 1);
+		}
+	}
+	match datetime::parse_iso(&date) {
+		Some(seconds) => {
+			lexer.push(Token::Number(seconds));
+			lexer.token_start = lexer.chars.pos();
+			lexer.push(Token::Unit(unit));
+			lexer.time_literal_end = Some(lexer.tokens.len());
+			Ok(())
+		},
+		None => Err(Error::InvalidDate { date, span: Some(lexer.span()) }),
+	}
+}
+
+/// If the next characters are `am` or `pm`, optionally after whitespace,
+/// returns how many characters that is and whether it's `pm`
+fn meridiem(chars: &Chars) -> Option<(usize, bool)> {
+	let mut n = 0;
+	while chars.peek_nth(n).is_some_and(|c| c.trim().is_empty()) {
+		n += 1;
+	}
+	let pm = match chars.peek_nth(n) {
+		Some("a") => false,
+		Some("p") => true,
+		_ => return None,
+	};
+	match (chars.peek_nth(n + 1), chars.peek_nth(n + 2)) {
+		(Some("m"), next) if !next.is_some_and(is_word_char_str) => Some((n + 2, pm)),
+		_ => None,
+	}
+}
+
+/// Read the rest of a time, when the first number was just read, like
+/// `:30`, `:30:15`, `:30pm`, ` am` or `:05.25`. `h:mm` and `h:mm:ss` are
+/// hours, minutes and seconds, and `mm:ss.sss` is minutes and seconds. Pushes
+/// the time in seconds, followed by a [`Second`] unit.
+///
+/// A time with two fields like `45:00` is read as `h:mm` here, but becomes
+/// `mm:ss` if the input also has a time with seconds, see [`lex`]. The
+/// [`Second`] unit has an empty span, which is how [`has_clock_time`] finds
+/// times.
+fn read_clock_time(first: &str, lexer: &mut Lexer) -> Result<(), Error> {
+	let mut time = first.to_owned();
+	let mut fields = vec![first.to_owned()];
+	while fields.len() < 3 && next_matches(&lexer.chars, ":dd") {
+		time += lexer.chars.next().unwrap();
+		let field = lexer.chars.next().unwrap().to_owned() + lexer.chars.next().unwrap();
+		time += &field;
+		fields.push(field);
+	}
+	let mut fraction = String::new();
+	if fields.len() > 1 && next_matches(&lexer.chars, ".d") {
+		while lexer.chars.peek().is_some_and(is_numeric_str) {
+			fraction += lexer.chars.next().unwrap();
+		}
+		time += &fraction;
+	}
+	let number = |field: Option<&String>, fraction: &str| match field {
+		Some(field) => d128::from_str(&(field.to_owned() + fraction)).unwrap(),
+		None => d128!(0),
+	};
+	// mm:ss.sss
+	let minutes_and_seconds = fields.len() == 2 && !fraction.is_empty();
+	let (hours, minutes, seconds) = match minutes_and_seconds {
+		true => (d128!(0), number(fields.first(), ""), number(fields.get(1), &fraction)),
+		false => (number(fields.first(), ""), number(fields.get(1), ""), number(fields.get(2), &fraction)),
+	};
+	let meridiem_len = meridiem(&lexer.chars).filter(|_| !minutes_and_seconds).map(|(len, _)| len);
+	let hours = match meridiem(&lexer.chars) {
+		Some((len, pm)) if !minutes_and_seconds => {
+			for _ in 0..len {
+				time += lexer.chars.next().unwrap();
+			}
+			match to_integer(hours) {
+				Some(12) if pm => d128!(12),
+				Some(12) => d128!(0),
+				Some(1..=11) if pm => hours + d128!(12),
+				Some(1..=11) => hours,
+				_ => return Err(Error::InvalidTime { time, span: Some(lexer.span()) }),
+			}
+		},
+		_ => hours,
+	};
+	if (!minutes_and_seconds && minutes >= d128!(60)) || seconds >= d128!(60) {
+		return Err(Error::InvalidTime { time, span: Some(lexer.span()) });
+	}
+	match fields.len() {
+		2 if !minutes_and_seconds && meridiem_len.is_none() => lexer.two_field_times.push(lexer.tokens.len()),
+		3 => lexer.has_time_with_seconds = true,
+		_ => {},
+	}
+	lexer.push(Token::Number(hours * d128!(3600) + minutes * d128!(60) + seconds));
+	lexer.token_start = lexer.chars.pos();
+	lexer.push(Token::Unit(Second));
+	lexer.time_literal_end = Some(lexer.tokens.len());
+	Ok(())
+}
+
+/// Returns true if the tokens have a time like `1:30:15`, `45:00` or `3pm`
+pub(crate) fn has_clock_time(tokens: &[SpannedToken]) -> bool {
+	tokens.iter().any(|t| t.token == Token::Unit(Second) && t.span.start == t.span.end)
+}
+
+/// Returns true if a time zone can come next, which is after `to` or `in`,
+/// or directly after a date or time like `2026-03-01`, `14:00` or `now`.
+/// Elsewhere, words like `paris` or `est` can be variable names.
+fn expects_time_zone(lexer: &Lexer) -> bool {
+	match lexer.tokens.last().map(|t| &t.token) {
+		Some(Token::TextOperator(To)) | Some(Token::LexerKeyword(In)) => true,
+		Some(Token::Constant(Now)) | Some(Token::Constant(Today)) => true,
+		_ => lexer.time_literal_end == Some(lexer.tokens.len()),
+	}
+}
+
+/// Read a time zone that starts with `word`, like `pst`, `tokyo` or
+/// `europe/berlin`. Names from the tz database can contain characters like
+/// `/` and `_`, so the longest name that follows is read.
+fn read_time_zone(word: &str, lexer: &mut Lexer) -> Result<Option<TimeZone>, Error> {
+	if matches!(lexer.chars.peek(), Some("/") | Some("_")) {
+		let mut name = word.to_owned();
+		let mut longest = None;
+		let mut n = 0;
+		while let Some(c) = lexer.chars.peek_nth(n) {
+			if !is_word_char_str(c) && !is_digit_str(c) && !matches!(c, "/" | "_" | "-" | "+") {
+				break;
+			}
+			name += c;
+			n += 1;
+			if let Some(zone) = TimeZone::from_name(&name) {
+				longest = Some((zone, n));
+			}
+		}
+		if let Some((zone, len)) = longest {
+			for _ in 0..len {
+				lexer.chars.next();
+			}
+			return Ok(Some(zone));
+		}
+	}
+	match TimeZone::from_abbreviation(word) {
+		Ok(Some(zone)) => Ok(Some(zone)),
+		Ok(None) => Ok(TimeZone::from_name(word)),
+		Err(e) => Err(e.with_span(lexer.span())),
+	}
+}
+
+/// A [`Token`] of the currency with the ISO 4217 code `code`
+fn currency(code: &str) -> Token {
+	Token::Unit(Unit::Currency(Currency::new(code).unwrap()))
+}
+
+fn parse_token(c: &str, lexer: &mut Lexer) -> Result<(), Error> {
 	match c {
 		value if value.trim().is_empty() => {},
 		value if is_word_char_str(value) => {
 			parse_word(read_word(c, lexer).as_str(), lexer)?;
 		},
+		"0" if radix_prefix(&lexer.chars).is_some() => {
+			let radix = radix_prefix(&lexer.chars).unwrap();
+			let mut number_string = c.to_owned() + lexer.chars.next().unwrap();
+			let mut digits = String::new();
+			while let Some(digit) = lexer.chars.peek() {
+				if is_digit_in_radix(digit, radix) {
+					digits += digit;
+					number_string += digit;
+					lexer.chars.next();
+				} else {
+					break;
+				}
+			}
+			d128::set_status(decimal::Status::empty());
+			let number = u128::from_str_radix(&digits, radix).ok()
+				.and_then(|integer| d128::from_str(&integer.to_string()).ok());
+			match number {
+				Some(number) if d128::get_status().is_empty() => {
+					lexer.push(Token::Number(number));
+				},
+				_ => {
+					return Err(Error::InvalidNumber { number: number_string, span: Some(lexer.span()) });
+				},
+			}
+		},
 		value if is_numeric_str(value) => {
 			let mut number_string = value.to_owned();
 			while let Some(number_char) = lexer.chars.peek() {
 				if is_numeric_str(number_char) {
 					number_string += number_char;
 					lexer.chars.next();
+				} else if number_char == "," {
+					match number_comma(&number_string, lexer) {
+						NumberComma::ThousandsSeparator => {
+							lexer.chars.next();
+						},
+						NumberComma::Ambiguous => {
+							let start = lexer.chars.pos();
+							return Err(Error::AmbiguousComma { span: Some(Span::new(start, start + 1)) });
+						},
+						NumberComma::Invalid => {
+							// read the rest of the number for the error, like "1,5"
+							while lexer.chars.peek().is_some_and(|c| c == "," || is_numeric_str(c)) {
+								lexer.chars.next();
+							}
+							let number = lexer.chars.since(lexer.token_start).to_owned();
+							return Err(Error::InvalidNumber { number, span: Some(lexer.span()) });
+						},
+						NumberComma::Separator => break,
+					}
 				} else {
 					break;
 				}
 			}
+			// 2026-03-01
+			if number_string.len() == 4 && number_string.chars().all(|c| c.is_ascii_digit()) && next_matches(&lexer.chars, "-dd-dd") {
+				return read_date(&number_string, lexer);
+			}
+			// 14:00, 1:30:15, 3pm
+			let integer = number_string.chars().all(|c| c.is_ascii_digit());
+			if integer && (next_matches(&lexer.chars, ":dd") || number_string.len() <= 2 && meridiem(&lexer.chars).is_some()) {
+				return read_clock_time(&number_string, lexer);
+			}
+			read_exponent(&mut number_string, &mut lexer.chars);
 			d128::set_status(decimal::Status::empty());
 			match d128::from_str(&number_string) {
 				Ok(number) => {
 					if d128::get_status().is_empty() {
-						tokens.push(Token::Number(number));
+						lexer.push(Token::Number(number));
 					} else {
-						return Err(format!("Error lexing d128 number: {}", number_string));
+						return Err(Error::InvalidNumber { number: number_string, span: Some(lexer.span()) });
 					}
 				},
 				Err(_e) => {
-					return Err(format!("Error lexing d128 number: {}", number_string));
+					return Err(Error::InvalidNumber { number: number_string, span: Some(lexer.span()) });
 				}
 			};
 		},
-		"+" => tokens.push(Token::Operator(Plus)),
-		"-" => tokens.push(Token::Operator(Minus)),
-		"*" => tokens.push(Token::Operator(Multiply)),
-		"/" | "÷" => tokens.push(Token::Operator(Divide)),
-		"%" => tokens.push(Token::LexerKeyword(PercentChar)),
-		"^" => tokens.push(Token::Operator(Caret)),
-		"!" => tokens.push(Token::UnaryOperator(Factorial)),
+		"+" => lexer.push(Token::Operator(Plus)),
+		"-" => lexer.push(Token::Operator(Minus)),
+		"*" | "·" | "⋅" | "×" => lexer.push(Token::Operator(Multiply)),
+		"/" | "÷" => lexer.push(Token::Operator(Divide)),
+		"%" => lexer.push(Token::LexerKeyword(PercentChar)),
+		"^" => lexer.push(Token::Operator(Caret)),
+		"<" | ">" if lexer.chars.peek() == Some(c) => {
+			lexer.chars.next();
+			let operator = if c == "<" { ShiftLeft } else { ShiftRight };
+			lexer.push(Token::Operator(operator));
+		},
+		"!" => lexer.push(Token::UnaryOperator(Factorial)),
 		"(" => {
 			lexer.left_paren_count += 1;
-			tokens.push(Token::Operator(LeftParen));
+			let paren = match lexer.tokens.last().map(|t| &t.token) {
+				Some(Token::FunctionIdentifier(_)) => Paren::Call { spaced_commas: has_spaced_commas(lexer) },
+				_ => Paren::Group,
+			};
+			lexer.parens.push(paren);
+			lexer.push(Token::Operator(LeftParen));
 		},
 		")" => {
 			lexer.right_paren_count += 1;
-			tokens.push(Token::Operator(RightParen));
+			lexer.parens.pop();
+			lexer.push(Token::Operator(RightParen));
+		},
+		"π" => lexer.push(Token::Constant(Pi)),
+		"_" => lexer.push(Token::Ans),
+		"=" => lexer.push(Token::Assign),
+		"," => lexer.push(Token::Operator(Comma)),
+		"'" => lexer.push(Token::Unit(Foot)),
+		// between units, like "h:min:s"
+		":" if matches!(lexer.tokens.last().map(|t| &t.token), Some(Token::Unit(_)) | Some(Token::LexerKeyword(AmbiguousDegree)))
+			&& lexer.chars.peek().is_some_and(is_word_char_str) => {
+			lexer.push(Token::Operator(Plus));
 		},
-		"π" => tokens.push(Token::Constant(Pi)),
-		"'" => tokens.push(Token::Unit(Foot)),
-		"\"" | "“" | "”" | "″" => tokens.push(Token::LexerKeyword(DoubleQuotes)),
+		"°" => lexer.push(Token::LexerKeyword(AmbiguousDegree)),
+		"$" => lexer.push(currency("usd")),
+		"€" => lexer.push(currency("eur")),
+		"£" => lexer.push(currency("gbp")),
+		"¥" => lexer.push(currency("jpy")),
+		"₹" => lexer.push(currency("inr")),
+		"\"" | "“" | "”" | "″" => lexer.push(Token::LexerKeyword(DoubleQuotes)),
 		_ => {
-			return Err(format!("Invalid character: {}", c));
+			return Err(Error::InvalidCharacter { character: c.to_string(), span: Some(lexer.span()) });
 		},
 	}
 	Ok(())
 }
 
-fn parse_word_if_non_empty(word: &str, lexer: &mut Lexer) -> Result<(), String> {
+fn parse_word_if_non_empty(word: &str, lexer: &mut Lexer) -> Result<(), Error> {
 	match word {
 		"" => Ok(()),
 		_ => parse_word(word, lexer)
 	}
 }
 
-fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
+fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), Error> {
+	let word_end = lexer.chars.pos();
 	let token = match word {
 		"to" => Token::TextOperator(To),
 		"of" => Token::TextOperator(Of),
@@ -177,6 +504,43 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 
 		"pi" => Token::Constant(Pi),
 		"e" => Token::Constant(E),
+		"now" => Token::Constant(Now),
+		"today" => Token::Constant(Today),
+
+		"ans" => Token::Ans,
+
+		"bin" | "binary" => Token::NumberFormat(NumberFormat::Base(2)),
+		"oct" | "octal" => Token::NumberFormat(NumberFormat::Base(8)),
+		"dec" | "decimal" => Token::NumberFormat(NumberFormat::Decimal),
+		"hex" | "hexadecimal" => Token::NumberFormat(NumberFormat::Base(16)),
+		"h" | "hh" if next_matches(&lexer.chars, ":mm:ss") => {
+			for _ in 0..6 {
+				lexer.chars.next();
+			}
+			Token::NumberFormat(NumberFormat::Clock)
+		},
+		"base" => {
+			while let Some(c) = lexer.chars.peek() {
+				if c.trim().is_empty() {
+					lexer.chars.next();
+				} else {
+					break;
+				}
+			}
+			let mut base = String::new();
+			while let Some(digit) = lexer.chars.peek() {
+				if is_digit_str(digit) {
+					base += digit;
+					lexer.chars.next();
+				} else {
+					break;
+				}
+			}
+			match base.parse() {
+				Ok(base) if (2..=36).contains(&base) => Token::NumberFormat(NumberFormat::Base(base)),
+				_ => return Err(Error::InvalidBase { base, span: Some(lexer.span()) }),
+			}
+		},
 
 		"plus" => Token::Operator(Plus),
 		"minus" => Token::Operator(Minus),
@@ -184,16 +548,20 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 		"multiplied" => {
 			match read_word("", lexer).as_str() {
 				"by" => Token::Operator(Multiply),
-				string => return Err(format!("Invalid string: {}", string)),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		},
 		"divided" => {
 			match read_word("", lexer).as_str() {
 				"by" => Token::Operator(Divide),
-				string => return Err(format!("Invalid string: {}", string)),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		},
 		"mod" => Token::Operator(Modulo),
+		"and" => Token::Operator(BitAnd),
+		"or" => Token::Operator(BitOr),
+		"xor" => Token::Operator(BitXor),
+		"not" => Token::Operator(BitNot),
 
 		"sqrt" => Token::FunctionIdentifier(Sqrt),
 		"cbrt" => Token::FunctionIdentifier(Cbrt),
@@ -201,15 +569,37 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 		"log" => Token::FunctionIdentifier(Log),
 		"ln" => Token::FunctionIdentifier(Ln),
 		"exp" => Token::FunctionIdentifier(Exp),
+		"root" => Token::FunctionIdentifier(Root),
 
 		"round" | "rint" => Token::FunctionIdentifier(Round),
 		"ceil" => Token::FunctionIdentifier(Ceil),
 		"floor" => Token::FunctionIdentifier(Floor),
 		"abs" | "fabs" => Token::FunctionIdentifier(Abs),
 
+		// "min" is minutes unless it's called like a function
+		"min" if is_function_call(lexer) => Token::FunctionIdentifier(Min),
+		"max" => Token::FunctionIdentifier(Max),
+		"clamp" => Token::FunctionIdentifier(Clamp),
+		"hypot" => Token::FunctionIdentifier(Hypot),
+
 		"sin" => Token::FunctionIdentifier(Sin),
 		"cos" => Token::FunctionIdentifier(Cos),
 		"tan" => Token::FunctionIdentifier(Tan),
+		// "sec" is seconds unless it's called like a function
+		"sec" if is_function_call(lexer) => Token::FunctionIdentifier(Sec),
+		"csc" | "cosec" => Token::FunctionIdentifier(Csc),
+		"cot" | "cotan" => Token::FunctionIdentifier(Cot),
+		"asin" | "arcsin" => Token::FunctionIdentifier(Asin),
+		"acos" | "arccos" => Token::FunctionIdentifier(Acos),
+		"atan" | "arctan" => Token::FunctionIdentifier(Atan),
+		"atan2" => Token::FunctionIdentifier(Atan2),
+
+		"sinh" => Token::FunctionIdentifier(Sinh),
+		"cosh" => Token::FunctionIdentifier(Cosh),
+		"tanh" => Token::FunctionIdentifier(Tanh),
+		"asinh" | "arsinh" | "arcsinh" => Token::FunctionIdentifier(Asinh),
+		"acosh" | "arcosh" | "arccosh" => Token::FunctionIdentifier(Acosh),
+		"atanh" | "artanh" | "arctanh" => Token::FunctionIdentifier(Atanh),
 
 		"per" => Token::LexerKeyword(Per),
 		"hg" => Token::LexerKeyword(Hg), // can be hectogram or mercury
@@ -219,6 +609,12 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 		"µs" | "μs" | "microsec" | "microsecs" | "microsecond" | "microseconds" => Token::Unit(Microsecond),
 		"ms" | "millisec" | "millisecs" | "millisecond" | "milliseconds" => Token::Unit(Millisecond),
 		"s" | "sec" | "secs" | "second" | "seconds" => Token::Unit(Second),
+		"s2" | "sec2" => {
+			// like the s² in m/s², which is s^2
+			lexer.push_until(Token::Unit(Second), word_end - 1);
+			lexer.push(Token::Operator(Caret));
+			Token::Number(d128!(2))
+		},
 		"min" | "mins" | "minute" | "minutes" => Token::Unit(Minute),
 		"h" | "hr" | "hrs" | "hour" | "hours" => Token::Unit(Hour),
 		"day" | "days" => Token::Unit(Day),
@@ -245,18 +641,44 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 		"nautical" => {
 			match read_word("", lexer).as_str() {
 				"mile" | "miles" => Token::Unit(NauticalMile),
-				string => return Err(format!("Invalid string: {}", string)),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		},
 		"ly" | "lightyear" | "lightyears" => Token::Unit(LightYear),
 		"lightsec" | "lightsecs" | "lightsecond" | "lightseconds" => Token::Unit(LightSecond),
+		"lightmin" | "lightmins" | "lightminute" | "lightminutes" => Token::Unit(LightMinute),
 		"light" => {
 			match read_word("", lexer).as_str() {
 				"yr" | "yrs" | "year" | "years" => Token::Unit(LightYear),
 				"sec" | "secs" | "second" | "seconds" => Token::Unit(LightSecond),
-				string => return Err(format!("Invalid string: {}", string)),
+				"min" | "mins" | "minute" | "minutes" => Token::Unit(LightMinute),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		}
+		"au" => Token::Unit(AstronomicalUnit),
+		"astronomical" => {
+			match read_word("", lexer).as_str() {
+				"unit" | "units" => Token::Unit(AstronomicalUnit),
+				string => return Err(lexer.unknown_word(string)),
+			}
+		}
+		"pc" | "parsec" | "parsecs" => Token::Unit(Parsec),
+		"earth" => {
+			match read_word("", lexer).as_str() {
+				"radius" | "radii" => Token::Unit(EarthRadius),
+				string => return Err(lexer.unknown_word(string)),
+			}
+		}
+		"picometer" | "picometers" | "picometre" | "picometres" => Token::Unit(Picometer),
+		"Å" | "Å" | "Å" | "å" | "angstrom" | "angstroms" => Token::Unit(Angstrom),
+		"nm" | "nanometer" | "nanometers" | "nanometre" | "nanometres" => Token::Unit(Nanometer),
+		"µm" | "μm" | "um" | "micrometer" | "micrometers" | "micrometre" | "micrometres" | "micron" | "microns" => Token::Unit(Micrometer),
+		"thou" | "mils" => Token::Unit(Thou),
+		"ftm" | "fathom" | "fathoms" => Token::Unit(Fathom),
+		"rod" | "rods" => Token::Unit(Rod),
+		"chain" | "chains" => Token::Unit(Chain),
+		"furlong" | "furlongs" => Token::Unit(Furlong),
+		"league" | "leagues" => Token::Unit(League),
 
 		"sqmm" | "mm2" | "millimeter2" | "millimeters2" | "millimetre2" | "millimetres2" => Token::Unit(SquareMillimeter),
 		"sqcm" | "cm2" | "centimeter2" | "centimeters2" | "centimetre2" | "centimetres2" => Token::Unit(SquareCentimeter),
@@ -278,7 +700,7 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 				"ft" | "foot" | "feet" => Token::Unit(SquareFoot),
 				"yd" | "yard" | "yards" => Token::Unit(SquareYard),
 				"mi" | "mile" | "miles" => Token::Unit(SquareMile),
-				string => return Err(format!("Invalid string: {}", string)),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		}
 		"are" | "ares" => Token::Unit(Are),
@@ -306,7 +728,7 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 				"ft" | "foot" | "feet" => Token::Unit(CubicFoot),
 				"yd" | "yard" | "yards" => Token::Unit(CubicYard),
 				"mi" | "mile" | "miles" => Token::Unit(CubicMile),
-				string => return Err(format!("Invalid string: {}", string)),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		},
 		"ml" | "milliliter" | "milliliters" | "millilitre" | "millilitres" => Token::Unit(Milliliter),
@@ -319,7 +741,7 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 		"fl" | "fluid" => {
 			match read_word("", lexer).as_str() {
 				"oz" | "ounce" | "ounces" => Token::Unit(FluidOunce),
-				string => return Err(format!("Invalid string: {}", string)),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		},
 		"cup" | "cups" => Token::Unit(Cup),
@@ -330,7 +752,7 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 		"oil" => {
 			match read_word("", lexer).as_str() {
 				"barrel" | "barrels" => Token::Unit(OilBarrel),
-				string => return Err(format!("Invalid string: {}", string)),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		},
 
@@ -338,12 +760,21 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 			match read_word("", lexer).as_str() {
 				"ton" | "tons" | "tonne" | "tonnes" => Token::Unit(MetricTon),
 				"hp" | "hps" | "horsepower" | "horsepowers" => Token::Unit(MetricHorsepower),
-				string => return Err(format!("Invalid string: {}", string)),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		},
 
 		"mg" | "milligram" | "milligrams" => Token::Unit(Milligram),
-		"g" | "gram" | "grams" => Token::Unit(Gram),
+		"g" => {
+			// g0 is standard gravity
+			if lexer.chars.peek() == Some("0") {
+				lexer.chars.next();
+				Token::Unit(StandardGravity)
+			} else {
+				Token::Unit(Gram)
+			}
+		},
+		"gram" | "grams" => Token::Unit(Gram),
 		"hectogram" | "hectograms" => Token::Unit(Hectogram),
 		"kg" | "kilo" | "kilos" | "kilogram" | "kilograms" => Token::Unit(Kilogram),
 		"t" | "tonne" | "tonnes" => Token::Unit(MetricTon),
@@ -353,22 +784,22 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 			match lexer.chars.next() {
 				Some("-") => {
 					match read_word_plain(&mut lexer.chars).as_str() {
-						"force" => Token::LexerKeyword(PoundForce),
+						"force" => Token::Unit(PoundForce),
 						other => {
-							lexer.tokens.push(Token::Unit(Pound));
-							lexer.tokens.push(Token::Operator(Minus));
+							lexer.push_until(Token::Unit(Pound), word_end);
+							lexer.push_until(Token::Operator(Minus), word_end + 1);
 							parse_word_if_non_empty(other, lexer)?;
 							return Ok(());
 						}
 					}
 				},
 				Some(c) => {
-					lexer.tokens.push(Token::Unit(Pound));
+					lexer.push_until(Token::Unit(Pound), word_end);
 					parse_token(c, lexer)?;
 					return Ok(());
 				},
 				None => {
-					lexer.tokens.push(Token::Unit(Pound));
+					lexer.push(Token::Unit(Pound));
 					return Ok(());
 				},
 			}
@@ -378,14 +809,14 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 		"short" => {
 			match read_word("", lexer).as_str() {
 				"ton" | "tons" | "tonne" | "tonnes" => Token::Unit(ShortTon),
-				string => return Err(format!("Invalid string: {}", string)),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		},
 		"lt" => Token::Unit(LongTon),
 		"long" => {
 			match read_word("", lexer).as_str() {
 				"ton" | "tons" | "tonne" | "tonnes" => Token::Unit(LongTon),
-				string => return Err(format!("Invalid string: {}", string)),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		},
 
@@ -436,22 +867,28 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 
 		"millijoule" | "millijoules" => Token::Unit(Millijoule),
 		"j"| "joule" | "joules" => Token::Unit(Joule),
-		"nm" => Token::Unit(NewtonMeter),
 		"newton" => {
-			match lexer.chars.next() {
-				Some("-") => {
-					match read_word_plain(&mut lexer.chars).as_str() {
-						"meter" | "meters" | "metre" | "metres" => Token::Unit(NewtonMeter),
-						string => return Err(format!("Invalid string: {}", string)),
+			if lexer.chars.peek() == Some("-") {
+				lexer.chars.next();
+				match read_word_plain(&mut lexer.chars).as_str() {
+					"meter" | "meters" | "metre" | "metres" => Token::Unit(NewtonMeter),
+					other => {
+						lexer.push_until(Token::Unit(Newton), word_end);
+						lexer.push_until(Token::Operator(Minus), word_end + 1);
+						parse_word_if_non_empty(other, lexer)?;
+						return Ok(());
 					}
-				},
-				Some(c) => {
-					match read_word(c, lexer).as_str() {
-						"meter" | "meters" | "metre" | "metres" => Token::Unit(NewtonMeter),
-						string => return Err(format!("Invalid string: {}", string)),
-					}
-				},
-				None => return Err(format!("Invalid string: {}", word)),
+				}
+			} else {
+				match read_word("", lexer).as_str() {
+					"meter" | "meters" | "metre" | "metres" => Token::Unit(NewtonMeter),
+					other => {
+						lexer.push_until(Token::Unit(Newton), word_end);
+						lexer.token_start = lexer.word_start;
+						parse_word_if_non_empty(other, lexer)?;
+						return Ok(());
+					},
+				}
 			}
 		},
 		"kj" | "kilojoule" | "kilojoules" => Token::Unit(Kilojoule),
@@ -466,10 +903,10 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 				"thermal" => {
 					match read_word("", lexer).as_str() {
 						"unit" | "units" => Token::Unit(BritishThermalUnit),
-						string => return Err(format!("Invalid string: {}", string)),
+						string => return Err(lexer.unknown_word(string)),
 					}
 				},
-				string => return Err(format!("Invalid string: {}", string)),
+				string => return Err(lexer.unknown_word(string)),
 			}
 		},
 		"wh" => Token::Unit(WattHour),
@@ -493,7 +930,8 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 			match read_word("", lexer).as_str() {
 				"hr" | "hrs" | "hour" | "hours" => Token::Unit(WattHour),
 				other => {
-					lexer.tokens.push(Token::Unit(Watt));
+					lexer.push_until(Token::Unit(Watt), word_end);
+					lexer.token_start = lexer.word_start;
 					parse_word_if_non_empty(other, lexer)?;
 					return Ok(());
 				},
@@ -503,7 +941,8 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 			match read_word("", lexer).as_str() {
 				"hr" | "hrs" | "hour" | "hours" => Token::Unit(KilowattHour),
 				other => {
-					lexer.tokens.push(Token::Unit(Kilowatt));
+					lexer.push_until(Token::Unit(Kilowatt), word_end);
+					lexer.token_start = lexer.word_start;
 					parse_word_if_non_empty(other, lexer)?;
 					return Ok(());
 				},
@@ -513,7 +952,8 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 			match read_word("", lexer).as_str() {
 				"hr" | "hrs" | "hour" | "hours" => Token::Unit(MegawattHour),
 				other => {
-					lexer.tokens.push(Token::Unit(Megawatt));
+					lexer.push_until(Token::Unit(Megawatt), word_end);
+					lexer.token_start = lexer.word_start;
 					parse_word_if_non_empty(other, lexer)?;
 					return Ok(());
 				},
@@ -523,7 +963,8 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 			match read_word("", lexer).as_str() {
 				"hr" | "hrs" | "hour" | "hours" => Token::Unit(GigawattHour),
 				other => {
-					lexer.tokens.push(Token::Unit(Gigawatt));
+					lexer.push_until(Token::Unit(Gigawatt), word_end);
+					lexer.token_start = lexer.word_start;
 					parse_word_if_non_empty(other, lexer)?;
 					return Ok(());
 				},
@@ -533,7 +974,8 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 			match read_word("", lexer).as_str() {
 				"hr" | "hrs" | "hour" | "hours" => Token::Unit(TerawattHour),
 				other => {
-					lexer.tokens.push(Token::Unit(Terawatt));
+					lexer.push_until(Token::Unit(Terawatt), word_end);
+					lexer.token_start = lexer.word_start;
 					parse_word_if_non_empty(other, lexer)?;
 					return Ok(());
 				},
@@ -543,7 +985,8 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 			match read_word("", lexer).as_str() {
 				"hr" | "hrs" | "hour" | "hours" => Token::Unit(PetawattHour),
 				other => {
-					lexer.tokens.push(Token::Unit(Petawatt));
+					lexer.push_until(Token::Unit(Petawatt), word_end);
+					lexer.token_start = lexer.word_start;
 					parse_word_if_non_empty(other, lexer)?;
 					return Ok(());
 				},
@@ -563,8 +1006,46 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 		"v" | "volt" | "volts" => Token::Unit(Volt),
 		"kv" | "kilovolt" | "kilovolts" => Token::Unit(Kilovolt),
 
-		// for pound-force per square inch
-		"lbf" => Token::LexerKeyword(PoundForce),
+		"n" | "newtons" => Token::Unit(Newton),
+		"kilonewton" | "kilonewtons" => Token::Unit(Kilonewton),
+		"meganewton" | "meganewtons" => Token::Unit(Meganewton),
+		"dyn" | "dyne" | "dynes" => Token::Unit(Dyne),
+		"lbf" => Token::Unit(PoundForce),
+		"kgf" | "kp" | "kilopond" | "kiloponds" => Token::Unit(KilogramForce),
+		"kip" | "kips" => Token::Unit(Kip),
+		"pdl" | "poundal" | "poundals" => Token::Unit(Poundal),
+		"µc" | "μc" | "uc" | "microcoulomb" | "microcoulombs" => Token::Unit(Microcoulomb),
+		"mc" | "millicoulomb" | "millicoulombs" => Token::Unit(Millicoulomb),
+		"coulomb" | "coulombs" => Token::Unit(Coulomb),
+		"mah" | "milliamphour" | "milliamphours" => Token::Unit(MilliampereHour),
+		"ah" | "amphour" | "amphours" => Token::Unit(AmpereHour),
+
+		"pf" | "picofarad" | "picofarads" => Token::Unit(Picofarad),
+		"nf" | "nanofarad" | "nanofarads" => Token::Unit(Nanofarad),
+		"µf" | "μf" | "uf" | "microfarad" | "microfarads" => Token::Unit(Microfarad),
+		"mf" | "millifarad" | "millifarads" => Token::Unit(Millifarad),
+		"farad" | "farads" => Token::Unit(Farad),
+
+		"nh" | "nanohenry" | "nanohenries" | "nanohenrys" => Token::Unit(Nanohenry),
+		"µh" | "μh" | "uh" | "microhenry" | "microhenries" | "microhenrys" => Token::Unit(Microhenry),
+		"mh" | "millihenry" | "millihenries" | "millihenrys" => Token::Unit(Millihenry),
+		"henry" | "henries" | "henrys" => Token::Unit(Henry),
+
+		"microsiemens" => Token::Unit(Microsiemens),
+		"millisiemens" => Token::Unit(Millisiemens),
+		"siemens" | "mho" | "mhos" => Token::Unit(Siemens),
+
+		"mx" | "maxwell" | "maxwells" => Token::Unit(Maxwell),
+		"mwb" | "milliweber" | "milliwebers" => Token::Unit(Milliweber),
+		"wb" | "weber" | "webers" => Token::Unit(Weber),
+
+		"nt" | "nanotesla" | "nanoteslas" => Token::Unit(Nanotesla),
+		"µt" | "μt" | "ut" | "microtesla" | "microteslas" => Token::Unit(Microtesla),
+		"mt" | "millitesla" | "milliteslas" => Token::Unit(Millitesla),
+		"gauss" => Token::Unit(Gauss),
+		"tesla" | "teslas" => Token::Unit(Tesla),
+
+		// for pound force and kilogram force
 		"force" => Token::LexerKeyword(Force),
 
 		"pa" | "pascal" | "pascals" => Token::Unit(Pascal),
@@ -586,36 +1067,268 @@ fn parse_word(word: &str, lexer: &mut Lexer) -> Result<(), String> {
 		"rpm" => Token::Unit(RevolutionsPerMinute),
 		"r" | "rev" | "revolution" | "revolutions" => Token::LexerKeyword(Revolution),
 
+		"gn" => Token::Unit(StandardGravity),
+		"standard" => {
+			match read_word("", lexer).as_str() {
+				"gravity" | "gravities" => Token::Unit(StandardGravity),
+				string => return Err(lexer.unknown_word(string)),
+			}
+		},
+		"galileo" | "galileos" => Token::Unit(Gal),
+
 		"kph" | "kmh" => Token::Unit(KilometersPerHour),
 		"mps" => Token::Unit(MetersPerSecond),
 		"mph" => Token::Unit(MilesPerHour),
 		"fps" => Token::Unit(FeetPerSecond),
 		"kn" | "kt" | "knot" | "knots" => Token::Unit(Knot),
 
+		"lph" => Token::Unit(LitersPerHour),
+		"lpm" => Token::Unit(LitersPerMinute),
+		"cfm" => Token::Unit(CubicFeetPerMinute),
+		"cfs" => Token::Unit(CubicFeetPerSecond),
+		"gph" => Token::Unit(GallonsPerHour),
+		"gpm" => Token::Unit(GallonsPerMinute),
+		"pcf" => Token::Unit(PoundsPerCubicFoot),
+
+		"kpl" | "kmpl" => Token::Unit(KilometersPerLiter),
+		"mpg" => Token::Unit(MilesPerGallon),
+		"mpgimp" | "impmpg" => Token::Unit(MilesPerImperialGallon),
+
 		"k" | "kelvin" | "kelvins" => Token::Unit(Kelvin),
 		"c" | "celsius" => Token::Unit(Celsius),
 		"f" | "fahrenheit" | "fahrenheits" => Token::Unit(Fahrenheit),
-		"deg" | "degree" | "degrees" => Token::Unit(lexer.default_degree),
+		"deg" | "degree" | "degrees" => Token::LexerKeyword(AmbiguousDegree),
 
-		string => {
-			return Err(format!("Invalid string: {}", string));
-		}
+		"rad" | "rads" | "radian" | "radians" => Token::Unit(Radian),
+		"grad" | "grads" | "gradian" | "gradians" | "gon" | "gons" => Token::Unit(Gradian),
+		"turn" | "turns" => Token::Unit(Turn),
+		"arcmin" | "arcmins" | "arcminute" | "arcminutes" => Token::Unit(Arcminute),
+		"arcsec" | "arcsecs" | "arcsecond" | "arcseconds" => Token::Unit(Arcsecond),
+
+		"date" | "dates" => Token::Unit(Date),
+		"datetime" | "datetimes" => Token::Unit(DateTime),
+		"unix" | "timestamp" | "timestamps" => Token::Unit(UnixTimestamp),
+
+		code @ ("usd" | "eur" | "gbp" | "jpy" | "cny" | "chf" | "cad" | "aud" | "nzd" | "sek" | "nok" | "dkk"
+			| "pln" | "czk" | "huf" | "ron" | "bgn" | "isk" | "inr" | "krw" | "sgd" | "hkd" | "twd" | "thb"
+			| "myr" | "idr" | "php" | "vnd" | "ils" | "try" | "aed" | "sar" | "zar" | "brl" | "mxn" | "ars"
+			| "clp" | "cop" | "pen" | "uah" | "egp" | "ngn" | "kes" | "pkr" | "bdt") => currency(code),
+		"dollar" | "dollars" => currency("usd"),
+		"euro" | "euros" => currency("eur"),
+		"sterling" => currency("gbp"),
+		"yen" => currency("jpy"),
+		"yuan" | "renminbi" => currency("cny"),
+		"rupee" | "rupees" => currency("inr"),
+		// other ISO 4217 currencies are only known if they have an exchange rate
+		code if Currency::new(code).is_some_and(|c| c.is_iso_4217() && c.weight().is_some()) => currency(code),
+
+		// anything else is a time zone where one is expected, or a variable name
+		string if expects_time_zone(lexer) => match read_time_zone(string, lexer)? {
+			Some(zone) => Token::Unit(Unit::Zoned(zone)),
+			None => Token::Identifier(string.to_string()),
+		},
+		string => Token::Identifier(string.to_string()),
 	};
-	lexer.tokens.push(token);
+	lexer.push(token);
 	Ok(())
 }
 
+/// The graphemes of the input, keeping track of the current byte position
+struct Chars<'a> {
+	graphemes: Graphemes<'a>,
+	input: &'a str,
+}
+
+impl<'a> Chars<'a> {
+	fn new(input: &'a str) -> Chars<'a> {
+		Chars {
+			graphemes: UnicodeSegmentation::graphemes(input, true),
+			input,
+		}
+	}
+	fn peek(&self) -> Option<&'a str> {
+		self.graphemes.clone().next()
+	}
+	/// Like [`peek`](Chars::peek), but `n` graphemes further ahead
+	fn peek_nth(&self, n: usize) -> Option<&'a str> {
+		self.graphemes.clone().nth(n)
+	}
+	/// Byte position of the next grapheme
+	fn pos(&self) -> usize {
+		self.input.len() - self.graphemes.as_str().len()
+	}
+	/// The input from the byte position `start` to the current position
+	fn since(&self, start: usize) -> &'a str {
+		&self.input[start..self.pos()]
+	}
+}
+
+impl<'a> Iterator for Chars<'a> {
+	type Item = &'a str;
+	fn next(&mut self) -> Option<&'a str> {
+		self.graphemes.next()
+	}
+}
+
 struct Lexer<'a> {
 	left_paren_count: u16,
 	right_paren_count: u16,
-	chars: Peekable<Graphemes<'a>>,
-	tokens: Vec<Token>,
-	default_degree: Unit,
+	chars: Chars<'a>,
+	tokens: Vec<SpannedToken>,
+	/// Byte position where the token currently being lexed starts
+	token_start: usize,
+	/// Byte position where the last word returned by [`read_word`] starts
+	word_start: usize,
+	/// The number of tokens right after the last date or time literal, like
+	/// `2026-03-01` or `14:00`, which can be followed by a time zone
+	time_literal_end: Option<usize>,
+	/// The parens that are open, innermost last
+	parens: Vec<Paren>,
+	/// The indexes of the tokens of times with two fields, like `45:00`
+	two_field_times: Vec<usize>,
+	/// Whether there was a time with seconds, like `1:30:15`
+	has_time_with_seconds: bool,
+}
+
+impl Lexer<'_> {
+	/// The [`Span`] of the original input from the start of the current token
+	/// to the current position
+	fn span(&self) -> Span {
+		Span::new(self.token_start, self.chars.pos())
+	}
+	/// Push a token spanning from the start of the current token to the
+	/// current position
+	fn push(&mut self, token: Token) {
+		let span = self.span();
+		self.tokens.push(SpannedToken { token, span });
+	}
+	/// Push a token that ends at `end`, which is before the current position,
+	/// and let the next token start there. Used when a word turns out not to
+	/// be the start of a multi-word unit.
+	fn push_until(&mut self, token: Token, end: usize) {
+		let span = Span::new(self.token_start, end);
+		self.tokens.push(SpannedToken { token, span });
+		self.token_start = end;
+	}
+	fn unknown_word(&self, word: &str) -> Error {
+		Error::UnknownWord { word: word.to_string(), span: Some(self.span()) }
+	}
+}
+
+/// An open paren
+#[derive(Clone, Copy)]
+enum Paren {
+	/// A paren that groups an expression, like in `2 * (3 + 4)`
+	Group,
+	/// The start of the arguments of a function call, like in `max(1, 2)`.
+	/// `spaced_commas` is true if any of the commas separating them are
+	/// followed by whitespace.
+	Call { spaced_commas: bool },
+}
+
+/// Returns true if a comma directly inside the paren that's about to be
+/// read is followed by whitespace, like in `max(1, 2)`
+fn has_spaced_commas(lexer: &Lexer) -> bool {
+	let mut depth = 0;
+	let mut n = 0;
+	while let Some(c) = lexer.chars.peek_nth(n) {
+		match c {
+			"(" => depth += 1,
+			")" if depth == 0 => break,
+			")" => depth -= 1,
+			"," if depth == 0 && lexer.chars.peek_nth(n + 1).is_some_and(|c| c.trim().is_empty()) => return true,
+			_ => {},
+		}
+		n += 1;
+	}
+	false
 }
 
-/// Lex an input string and returns [`Token`]s
-pub fn lex(input: &str, remove_trailing_operator: bool, default_degree: Unit) -> Result<Vec<Token>, String> {
-	let mut input = input.replace(',', "").to_ascii_lowercase();
+/// What a comma directly after the digits of a number is
+enum NumberComma {
+	/// A thousands separator, like in `1,000` or `max((1,500), 2)`
+	ThousandsSeparator,
+	/// Either a thousands separator or an argument separator, like the
+	/// second comma in `max(1, 2,000)`
+	Ambiguous,
+	/// Neither, like in `1,5`
+	Invalid,
+	/// Not part of the number, like in `max(1,500)` or `atan2(1, 2)`
+	Separator,
+}
+
+/// Decides what the next character, which is a comma, is in `number_string`.
+///
+/// A comma between digits that's followed by exactly 3 digits is a thousands
+/// separator, except directly inside a function call. There, commas separate
+/// arguments, like in `max(1,500)`, but if other commas in the call are
+/// followed by a space, like in `max(1, 2,000)`, a comma followed by 3 digits
+/// is ambiguous.
+fn number_comma(number_string: &str, lexer: &Lexer) -> NumberComma {
+	let is_digit = |n: usize| lexer.chars.peek_nth(n).is_some_and(is_digit_str);
+	if !number_string.ends_with(|c: char| c.is_ascii_digit()) || !is_digit(1) {
+		return NumberComma::Separator;
+	}
+	let three_digits = (1..=3).all(is_digit) && !is_digit(4);
+	match (lexer.parens.last(), three_digits) {
+		(Some(Paren::Call { spaced_commas: true }), true) => NumberComma::Ambiguous,
+		(Some(Paren::Call { .. }), _) => NumberComma::Separator,
+		(_, true) => NumberComma::ThousandsSeparator,
+		(_, false) => NumberComma::Invalid,
+	}
+}
+
+/// Returns true if the function name that was just read is called, which is
+/// when it's followed by `(`, optionally after whitespace, and doesn't come
+/// after a number. Used for `sec` and `min`, which are also units, so `5 min`
+/// is a time, but `min (3, 4)` is a function call.
+fn is_function_call(lexer: &Lexer) -> bool {
+	let mut n = 0;
+	while lexer.chars.peek_nth(n).is_some_and(|c| c.trim().is_empty()) {
+		n += 1;
+	}
+	lexer.chars.peek_nth(n) == Some("(") && !matches!(lexer.tokens.last().map(|t| &t.token), Some(Token::Number(_)))
+}
+
+/// Returns true if the [`ShortTon`] at `index` is written as `st` and comes
+/// before pounds, like in `13 st 7 lb` or `to st+lb`
+fn is_stone(tokens: &[SpannedToken], index: usize, input: &str) -> bool {
+	let token = |i: usize| tokens.get(i).map(|t| &t.token);
+	let span = tokens[index].span;
+	let before_pounds = matches!(token(index + 1), Some(Token::Number(_)) | Some(Token::Operator(Plus))) && token(index + 2) == Some(&Token::Unit(Pound));
+	input[span.start..span.end].eq_ignore_ascii_case("st") && before_pounds
+}
+
+/// Returns true if the [`Celsius`] at `index` is written as an uppercase `C`
+/// and comes after a number in E notation, like in `1.6E-19 C`
+fn is_coulombs(tokens: &[SpannedToken], index: usize, input: &str) -> bool {
+	let span = tokens[index].span;
+	let after_e_notation = index >= 1 && match &tokens[index - 1] {
+		SpannedToken { token: Token::Number(_), span } => {
+			let number = &input[span.start..span.end];
+			!number.starts_with("0x") && !number.starts_with("0X") && number.contains(['e', 'E'])
+		},
+		_ => false,
+	};
+	&input[span.start..span.end] == "C" && after_e_notation
+}
+
+/// Returns true if the [`Meter`] at `index` is written as `m` and comes after
+/// hours or before seconds, like in `2h 15m` or `15m 30s`
+fn is_minutes(tokens: &[SpannedToken], index: usize, input: &str) -> bool {
+	let token = |i: usize| tokens.get(i).map(|t| &t.token);
+	let span = tokens[index].span;
+	let after_hours = index >= 2 && token(index - 2) == Some(&Token::Unit(Hour)) && matches!(token(index - 1), Some(Token::Number(_)));
+	let before_seconds = matches!(token(index + 1), Some(Token::Number(_))) && token(index + 2) == Some(&Token::Unit(Second));
+	input[span.start..span.end].eq_ignore_ascii_case("m") && (after_hours || before_seconds)
+}
+
+/// Lex an input string and returns [`SpannedToken`]s
+///
+/// The [`Span`]s are byte ranges of `input`.
+pub fn lex(input: &str, remove_trailing_operator: bool, default_degree: Unit) -> Result<Vec<SpannedToken>, Error> {
+	let original_input = input;
+	let mut input = input.to_ascii_lowercase();
 
 	if remove_trailing_operator {
 		match &input.chars().last().unwrap_or('x') {
@@ -629,294 +1342,475 @@ pub fn lex(input: &str, remove_trailing_operator: bool, default_degree: Unit) ->
 	let mut lexer = Lexer {
 		left_paren_count: 0,
 		right_paren_count: 0,
-		chars: UnicodeSegmentation::graphemes(input.as_str(), true).peekable(),
+		chars: Chars::new(input.as_str()),
 		tokens: Vec::new(),
-		default_degree,
+		token_start: 0,
+		word_start: 0,
+		time_literal_end: None,
+		parens: Vec::new(),
+		two_field_times: Vec::new(),
+		has_time_with_seconds: false,
 	};
 
-	while let Some(c) = lexer.chars.next() {
-		parse_token(c, &mut lexer)?;
+	loop {
+		lexer.token_start = lexer.chars.pos();
+		match lexer.chars.next() {
+			Some(c) => parse_token(c, &mut lexer)?,
+			None => break,
+		}
 	}
 	let tokens = &mut lexer.tokens;
+	// times like 45:00 are hours and minutes, but next to a time with seconds
+	// like 1:30:15, they're minutes and seconds, like in "1:30:15 + 45:00"
+	if lexer.has_time_with_seconds {
+		for &index in &lexer.two_field_times {
+			if let Token::Number(seconds) = tokens[index].token {
+				tokens[index].token = Token::Number(seconds / d128!(60));
+			}
+		}
+	}
 	// auto insert missing parentheses in first and last position
 	if lexer.left_paren_count > lexer.right_paren_count {
 		let missing_right_parens = lexer.left_paren_count - lexer.right_paren_count;
+		let end = original_input.len();
 		for _ in 0..missing_right_parens {
-			tokens.push(Token::Operator(RightParen));
+			tokens.push(SpannedToken { token: Token::Operator(RightParen), span: Span::new(end, end) });
 		}
 	} else if lexer.left_paren_count < lexer.right_paren_count {
 		let missing_left_parens = lexer.right_paren_count - lexer.left_paren_count;
 		for _ in 0..missing_left_parens {
-			tokens.insert(0, Token::Operator(LeftParen));
+			tokens.insert(0, SpannedToken { token: Token::Operator(LeftParen), span: Span::new(0, 0) });
 		}
 	}
 
 	if tokens.is_empty() {
-		return Err("Input was empty".to_string());
+		return Err(Error::EmptyInput);
 	}
 
 	let mut token_index = 0;
 	loop {
-		match tokens[token_index] {
+		match tokens[token_index].token {
 			// decide if % is percent or modulo
 			Token::LexerKeyword(PercentChar) => {
-				match tokens.get(token_index + 1) {
+				match tokens.get(token_index + 1).map(|t| &t.token) {
 					Some(Token::TextOperator(Of)) => {
 						// "10% of 1km" should be percentage
-						tokens[token_index] = Token::UnaryOperator(Percent);
+						tokens[token_index].token = Token::UnaryOperator(Percent);
 					},
 					Some(Token::Operator(operator)) => {
 						match operator {
 							LeftParen => {
 								// "10%(2)" should be modulo
-								tokens[token_index] = Token::Operator(Modulo);
+								tokens[token_index].token = Token::Operator(Modulo);
 							},
 							_ => {
 								// "10%*2" should be a percentage
-								tokens[token_index] = Token::UnaryOperator(Percent);
+								tokens[token_index].token = Token::UnaryOperator(Percent);
 							}
 						}
 					},
 					Some(Token::UnaryOperator(_)) => {
 						// "10%!" should be a percentage
-						tokens[token_index] = Token::UnaryOperator(Percent);
+						tokens[token_index].token = Token::UnaryOperator(Percent);
 					},
 					Some(Token::LexerKeyword(PercentChar)) => {
 						// "10%%" should be a percentage
-						tokens[token_index] = Token::UnaryOperator(Percent);
+						tokens[token_index].token = Token::UnaryOperator(Percent);
 					},
 					None => {
 						// percent if there's no element afterwards
-						tokens[token_index] = Token::UnaryOperator(Percent);
+						tokens[token_index].token = Token::UnaryOperator(Percent);
 					},
 					_ => {
 						// everything else should be modulo, for example if the % is
 						// before a number, function or constants
-						tokens[token_index] = Token::Operator(Modulo);
+						tokens[token_index].token = Token::Operator(Modulo);
 					},
 				}
 			},
 			// decide if " is 'inch' or 'inch of mercury'
 			Token::LexerKeyword(DoubleQuotes) => {
-				match tokens.get(token_index + 1) {
+				match tokens.get(token_index + 1).map(|t| &t.token) {
 					Some(Token::LexerKeyword(Hg)) => {
 						// "hg should be inch of mercury
-						tokens[token_index] = Token::Unit(InchOfMercury);
+						tokens[token_index].token = Token::Unit(InchOfMercury);
+						tokens[token_index].span.end = tokens[token_index + 1].span.end;
 						tokens.remove(token_index + 1);
 					},
 					_ => {
 						// otherwise, Inch
-						tokens[token_index] = Token::Unit(Inch);
+						tokens[token_index].token = Token::Unit(Inch);
 					},
 				}
 			},
+			// "C" after a number in E notation is coulombs, like "1.6E-19 C"
+			Token::Unit(Celsius) if is_coulombs(tokens, token_index, original_input) => {
+				tokens[token_index].token = Token::Unit(Coulomb);
+			},
+			// "m" between hours and seconds is minutes, like "2h 15m 30s"
+			Token::Unit(Meter) if is_minutes(tokens, token_index, original_input) => {
+				tokens[token_index].token = Token::Unit(Minute);
+			},
+			// "nm" is nanometers, but "Nm" is newton meters
+			Token::Unit(Nanometer) if &original_input[tokens[token_index].span.start..tokens[token_index].span.end] == "Nm" => {
+				tokens[token_index].token = Token::Unit(NewtonMeter);
+			},
+			// "st" before pounds is stone, like "13 st 7 lb" or "to st+lb"
+			Token::Unit(ShortTon) if is_stone(tokens, token_index, original_input) => {
+				tokens[token_index].token = Token::Unit(Stone);
+			},
 			// if hg wasn't already turned into inch of mercury, it's hectogram
 			Token::LexerKeyword(Hg) => {
-				tokens[token_index] = Token::Unit(Hectogram);
+				tokens[token_index].token = Token::Unit(Hectogram);
 			},
 			// decide if "in" is Inch or To
 			Token::LexerKeyword(In) => {
-				match tokens.get(token_index + 1) {
-					Some(Token::Unit(_)) => {
+				match tokens.get(token_index + 1).map(|t| &t.token) {
+					Some(Token::Unit(_)) | Some(Token::NumberFormat(_)) | Some(Token::LexerKeyword(AmbiguousDegree)) => {
 						// "in" should be To
-						tokens[token_index] = Token::TextOperator(To);
+						tokens[token_index].token = Token::TextOperator(To);
 					},
 					_ => {
 						// otherwise, Inch
-						tokens[token_index] = Token::Unit(Inch);
+						tokens[token_index].token = Token::Unit(Inch);
 					},
 				}
 			},
 			_ => {},
 		}
-		// parse units like km/h, lbf per square inch
-		if token_index >= 2 {
-			let token1 = &tokens[token_index-2];
-			let token2 = match &tokens[token_index-1] {
+		// parse m/s² and ft/s²
+		if token_index >= 4 {
+			let per = matches!(tokens[token_index-3].token, Token::Operator(Divide) | Token::LexerKeyword(Per));
+			let acceleration = match tokens[token_index-4].token {
+				Token::Unit(Meter) => Some(MetersPerSecondSquared),
+				Token::Unit(Foot) => Some(FeetPerSecondSquared),
+				_ => None,
+			};
+			if let (true, Some(acceleration)) = (per, acceleration) {
+				if tokens[token_index-2].token == Token::Unit(Second)
+					&& tokens[token_index-1].token == Token::Operator(Caret)
+					&& tokens[token_index].token == Token::Number(d128!(2))
+				{
+					tokens[token_index-4].token = Token::Unit(acceleration);
+					tokens[token_index-4].span.end = tokens[token_index].span.end;
+					tokens.drain(token_index-3..=token_index);
+					token_index -= 4;
+				}
+			}
+		}
+		// parse l/100km
+		if token_index >= 3 {
+			let per = matches!(tokens[token_index-2].token, Token::Operator(Divide) | Token::LexerKeyword(Per));
+			if per
+				&& tokens[token_index-3].token == Token::Unit(Liter)
+				&& tokens[token_index-1].token == Token::Number(d128!(100))
+				&& tokens[token_index].token == Token::Unit(Kilometer)
+			{
+				tokens[token_index-3].token = Token::Unit(LitersPer100Kilometers);
+				tokens[token_index-3].span.end = tokens[token_index].span.end;
+				tokens.drain(token_index-2..=token_index);
+				token_index -= 3;
+			}
+		}
+		// parse units like km/h, lbf per square inch, but not m/s in
+		// kg*m/s^2, since the ^2 only belongs to the s
+		let followed_by_caret = matches!(tokens.get(token_index + 1).map(|t| &t.token), Some(Token::Operator(Caret)));
+		if token_index >= 2 && !followed_by_caret {
+			let token1 = &tokens[token_index-2].token;
+			let token2 = match &tokens[token_index-1].token {
 				// treat km/h the same as km per h
 				Token::Operator(Divide) => &Token::LexerKeyword(Per),
-				_ => &tokens[token_index-1],
+				token => token,
 			};
-			let token3 = &tokens[token_index];
+			let token3 = &tokens[token_index].token;
 			let mut replaced = true;
 			match (token1, token2, token3) {
 				// km/h
 				(Token::Unit(Kilometer), Token::LexerKeyword(Per), Token::Unit(Hour)) => {
-					tokens[token_index-2] = Token::Unit(KilometersPerHour);
+					tokens[token_index-2].token = Token::Unit(KilometersPerHour);
 				},
 				// mi/h
 				(Token::Unit(Mile), Token::LexerKeyword(Per), Token::Unit(Hour)) => {
-					tokens[token_index-2] = Token::Unit(MilesPerHour);
+					tokens[token_index-2].token = Token::Unit(MilesPerHour);
 				},
 				// m/s
 				(Token::Unit(Meter), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(MetersPerSecond);
+					tokens[token_index-2].token = Token::Unit(MetersPerSecond);
 				},
 				// ft/s
 				(Token::Unit(Foot), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(FeetPerSecond);
+					tokens[token_index-2].token = Token::Unit(FeetPerSecond);
 				},
 				// bits per second
 				(Token::Unit(Bit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(BitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(BitsPerSecond);
 				},
 				// kilobits per second
 				(Token::Unit(Kilobit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(KilobitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(KilobitsPerSecond);
 				},
 				// megabits per second
 				(Token::Unit(Megabit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(MegabitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(MegabitsPerSecond);
 				},
 				// gigabits per second
 				(Token::Unit(Gigabit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(GigabitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(GigabitsPerSecond);
 				},
 				// terabits per second
 				(Token::Unit(Terabit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(TerabitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(TerabitsPerSecond);
 				},
 				// petabits per second
 				(Token::Unit(Petabit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(PetabitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(PetabitsPerSecond);
 				},
 				// exabits per second
 				(Token::Unit(Exabit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(ExabitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(ExabitsPerSecond);
 				},
 				// zettabits per second
 				(Token::Unit(Zettabit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(ZettabitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(ZettabitsPerSecond);
 				},
 				// yottabits per second
 				(Token::Unit(Yottabit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(YottabitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(YottabitsPerSecond);
 				},
 				// kibibits per second
 				(Token::Unit(Kibibit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(KibibitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(KibibitsPerSecond);
 				},
 				// mebibits per second
 				(Token::Unit(Mebibit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(MebibitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(MebibitsPerSecond);
 				},
 				// gibibits per second
 				(Token::Unit(Gibibit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(GibibitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(GibibitsPerSecond);
 				},
 				// tebibits per second
 				(Token::Unit(Tebibit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(TebibitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(TebibitsPerSecond);
 				},
 				// pebibits per second
 				(Token::Unit(Pebibit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(PebibitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(PebibitsPerSecond);
 				},
 				// exbibits per second
 				(Token::Unit(Exbibit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(ExbibitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(ExbibitsPerSecond);
 				},
 				// zebibits per second
 				(Token::Unit(Zebibit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(ZebibitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(ZebibitsPerSecond);
 				},
 				// yobibits per second
 				(Token::Unit(Yobibit), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(YobibitsPerSecond);
+					tokens[token_index-2].token = Token::Unit(YobibitsPerSecond);
 				},
 				// bytes per second
 				(Token::Unit(Byte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(BytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(BytesPerSecond);
 				},
 				// kilobytes per second
 				(Token::Unit(Kilobyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(KilobytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(KilobytesPerSecond);
 				},
 				// megabytes per second
 				(Token::Unit(Megabyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(MegabytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(MegabytesPerSecond);
 				},
 				// gigabytes per second
 				(Token::Unit(Gigabyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(GigabytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(GigabytesPerSecond);
 				},
 				// terabytes per second
 				(Token::Unit(Terabyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(TerabytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(TerabytesPerSecond);
 				},
 				// petabytes per second
 				(Token::Unit(Petabyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(PetabytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(PetabytesPerSecond);
 				},
 				// exabytes per second
 				(Token::Unit(Exabyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(ExabytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(ExabytesPerSecond);
 				},
 				// zettabytes per second
 				(Token::Unit(Zettabyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(ZettabytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(ZettabytesPerSecond);
 				},
 				// yottabytes per second
 				(Token::Unit(Yottabyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(YottabytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(YottabytesPerSecond);
 				},
 				// kibibytes per second
 				(Token::Unit(Kibibyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(KibibytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(KibibytesPerSecond);
 				},
 				// mebibytes per second
 				(Token::Unit(Mebibyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(MebibytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(MebibytesPerSecond);
 				},
 				// gibibytes per second
 				(Token::Unit(Gibibyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(GibibytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(GibibytesPerSecond);
 				},
 				// tebibytes per second
 				(Token::Unit(Tebibyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(TebibytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(TebibytesPerSecond);
 				},
 				// pebibytes per second
 				(Token::Unit(Pebibyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(PebibytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(PebibytesPerSecond);
 				},
 				// exbibytes per second
 				(Token::Unit(Exbibyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(ExbibytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(ExbibytesPerSecond);
 				},
 				// zebibytes per second
 				(Token::Unit(Zebibyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(ZebibytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(ZebibytesPerSecond);
 				},
 				// yobibytes per second
 				(Token::Unit(Yobibyte), Token::LexerKeyword(Per), Token::Unit(Second)) => {
-					tokens[token_index-2] = Token::Unit(YobibytesPerSecond);
+					tokens[token_index-2].token = Token::Unit(YobibytesPerSecond);
 				},
 				// btu/min
 				(Token::Unit(BritishThermalUnit), Token::LexerKeyword(Per), Token::Unit(Minute)) => {
-					tokens[token_index-2] = Token::Unit(BritishThermalUnitsPerMinute);
+					tokens[token_index-2].token = Token::Unit(BritishThermalUnitsPerMinute);
 				},
 				// btu/h
 				(Token::Unit(BritishThermalUnit), Token::LexerKeyword(Per), Token::Unit(Hour)) => {
-					tokens[token_index-2] = Token::Unit(BritishThermalUnitsPerHour);
+					tokens[token_index-2].token = Token::Unit(BritishThermalUnitsPerHour);
 				},
 				// lbs/sqin
-				(Token::LexerKeyword(PoundForce), Token::LexerKeyword(Per), Token::Unit(SquareInch)) => {
-					tokens[token_index-2] = Token::Unit(PoundsPerSquareInch);
+				(Token::Unit(PoundForce), Token::LexerKeyword(Per), Token::Unit(SquareInch)) => {
+					tokens[token_index-2].token = Token::Unit(PoundsPerSquareInch);
+				},
+				// l/h
+				(Token::Unit(Liter), Token::LexerKeyword(Per), Token::Unit(Hour)) => {
+					tokens[token_index-2].token = Token::Unit(LitersPerHour);
+				},
+				// l/min
+				(Token::Unit(Liter), Token::LexerKeyword(Per), Token::Unit(Minute)) => {
+					tokens[token_index-2].token = Token::Unit(LitersPerMinute);
+				},
+				// l/s
+				(Token::Unit(Liter), Token::LexerKeyword(Per), Token::Unit(Second)) => {
+					tokens[token_index-2].token = Token::Unit(LitersPerSecond);
+				},
+				// m3/h
+				(Token::Unit(CubicMeter), Token::LexerKeyword(Per), Token::Unit(Hour)) => {
+					tokens[token_index-2].token = Token::Unit(CubicMetersPerHour);
+				},
+				// m3/s
+				(Token::Unit(CubicMeter), Token::LexerKeyword(Per), Token::Unit(Second)) => {
+					tokens[token_index-2].token = Token::Unit(CubicMetersPerSecond);
+				},
+				// ft3/min
+				(Token::Unit(CubicFoot), Token::LexerKeyword(Per), Token::Unit(Minute)) => {
+					tokens[token_index-2].token = Token::Unit(CubicFeetPerMinute);
+				},
+				// ft3/s
+				(Token::Unit(CubicFoot), Token::LexerKeyword(Per), Token::Unit(Second)) => {
+					tokens[token_index-2].token = Token::Unit(CubicFeetPerSecond);
+				},
+				// gal/h
+				(Token::Unit(Gallon), Token::LexerKeyword(Per), Token::Unit(Hour)) => {
+					tokens[token_index-2].token = Token::Unit(GallonsPerHour);
+				},
+				// gal/min
+				(Token::Unit(Gallon), Token::LexerKeyword(Per), Token::Unit(Minute)) => {
+					tokens[token_index-2].token = Token::Unit(GallonsPerMinute);
+				},
+				// g/s
+				(Token::Unit(Gram), Token::LexerKeyword(Per), Token::Unit(Second)) => {
+					tokens[token_index-2].token = Token::Unit(GramsPerSecond);
+				},
+				// kg/h
+				(Token::Unit(Kilogram), Token::LexerKeyword(Per), Token::Unit(Hour)) => {
+					tokens[token_index-2].token = Token::Unit(KilogramsPerHour);
+				},
+				// kg/min
+				(Token::Unit(Kilogram), Token::LexerKeyword(Per), Token::Unit(Minute)) => {
+					tokens[token_index-2].token = Token::Unit(KilogramsPerMinute);
+				},
+				// kg/s
+				(Token::Unit(Kilogram), Token::LexerKeyword(Per), Token::Unit(Second)) => {
+					tokens[token_index-2].token = Token::Unit(KilogramsPerSecond);
+				},
+				// t/h
+				(Token::Unit(MetricTon), Token::LexerKeyword(Per), Token::Unit(Hour)) => {
+					tokens[token_index-2].token = Token::Unit(MetricTonsPerHour);
+				},
+				// lb/h
+				(Token::Unit(Pound), Token::LexerKeyword(Per), Token::Unit(Hour)) => {
+					tokens[token_index-2].token = Token::Unit(PoundsPerHour);
+				},
+				// lb/min
+				(Token::Unit(Pound), Token::LexerKeyword(Per), Token::Unit(Minute)) => {
+					tokens[token_index-2].token = Token::Unit(PoundsPerMinute);
+				},
+				// lb/s
+				(Token::Unit(Pound), Token::LexerKeyword(Per), Token::Unit(Second)) => {
+					tokens[token_index-2].token = Token::Unit(PoundsPerSecond);
+				},
+				// g/l
+				(Token::Unit(Gram), Token::LexerKeyword(Per), Token::Unit(Liter)) => {
+					tokens[token_index-2].token = Token::Unit(GramsPerLiter);
+				},
+				// kg/m3
+				(Token::Unit(Kilogram), Token::LexerKeyword(Per), Token::Unit(CubicMeter)) => {
+					tokens[token_index-2].token = Token::Unit(KilogramsPerCubicMeter);
+				},
+				// kg/l
+				(Token::Unit(Kilogram), Token::LexerKeyword(Per), Token::Unit(Liter)) => {
+					tokens[token_index-2].token = Token::Unit(KilogramsPerLiter);
+				},
+				// g/cm3
+				(Token::Unit(Gram), Token::LexerKeyword(Per), Token::Unit(CubicCentimeter)) => {
+					tokens[token_index-2].token = Token::Unit(GramsPerCubicCentimeter);
+				},
+				// g/ml
+				(Token::Unit(Gram), Token::LexerKeyword(Per), Token::Unit(Milliliter)) => {
+					tokens[token_index-2].token = Token::Unit(GramsPerMilliliter);
+				},
+				// lb/ft3
+				(Token::Unit(Pound), Token::LexerKeyword(Per), Token::Unit(CubicFoot)) => {
+					tokens[token_index-2].token = Token::Unit(PoundsPerCubicFoot);
+				},
+				// lb/in3
+				(Token::Unit(Pound), Token::LexerKeyword(Per), Token::Unit(CubicInch)) => {
+					tokens[token_index-2].token = Token::Unit(PoundsPerCubicInch);
+				},
+				// lb/gal
+				(Token::Unit(Pound), Token::LexerKeyword(Per), Token::Unit(Gallon)) => {
+					tokens[token_index-2].token = Token::Unit(PoundsPerGallon);
+				},
+				// km/l
+				(Token::Unit(Kilometer), Token::LexerKeyword(Per), Token::Unit(Liter)) => {
+					tokens[token_index-2].token = Token::Unit(KilometersPerLiter);
+				},
+				// mi/gal
+				(Token::Unit(Mile), Token::LexerKeyword(Per), Token::Unit(Gallon)) => {
+					tokens[token_index-2].token = Token::Unit(MilesPerGallon);
 				},
 				// inch of mercury
 				(Token::Unit(Inch), Token::TextOperator(Of), Token::LexerKeyword(Mercury)) => {
-					tokens[token_index-2] = Token::Unit(InchOfMercury);
+					tokens[token_index-2].token = Token::Unit(InchOfMercury);
 				},
 				// revolutions per minute
 				(Token::LexerKeyword(Revolution), Token::LexerKeyword(Per), Token::Unit(Minute)) => {
-					tokens[token_index-2] = Token::Unit(RevolutionsPerMinute);
+					tokens[token_index-2].token = Token::Unit(RevolutionsPerMinute);
 				},
 				_ => {
 					replaced = false;
 				},
 			}
 			if replaced {
+				tokens[token_index-2].span.end = tokens[token_index].span.end;
 				tokens.remove(token_index);
 				tokens.remove(token_index-1);
 				token_index -= 2;
@@ -929,6 +1823,112 @@ pub fn lex(input: &str, remove_trailing_operator: bool, default_degree: Unit) ->
 		}
 	}
 
+	// pound force and kilogram force, like "5 kg force" or "1 kilogram-force"
+	let mut token_index = 0;
+	while token_index < tokens.len() {
+		if tokens[token_index].token == Token::LexerKeyword(Force) {
+			let with_minus = matches!(tokens.get(token_index.wrapping_sub(1)).map(|t| &t.token), Some(Token::Operator(Minus)));
+			let unit_index = token_index.wrapping_sub(if with_minus { 2 } else { 1 });
+			let force_unit = match tokens.get(unit_index).map(|t| &t.token) {
+				Some(Token::Unit(Pound)) => Some(PoundForce),
+				Some(Token::Unit(Kilogram)) => Some(KilogramForce),
+				_ => None,
+			};
+			if let Some(force_unit) = force_unit {
+				tokens[unit_index].token = Token::Unit(force_unit);
+				tokens[unit_index].span.end = tokens[token_index].span.end;
+				tokens.drain(unit_index + 1..=token_index);
+				token_index = unit_index;
+			}
+		}
+		token_index += 1;
+	}
+
+	// "st" is the same unit everywhere in the input, so if there's stone, like
+	// in "13.5 st to st+lb" or "2 st to stone", every "st" is stone
+	let is_st = |token: &SpannedToken| original_input[token.span.start..token.span.end].eq_ignore_ascii_case("st");
+	if tokens.iter().any(|token| token.token == Token::Unit(Stone)) {
+		for token in tokens.iter_mut() {
+			if token.token == Token::Unit(ShortTon) && is_st(token) {
+				token.token = Token::Unit(Stone);
+			}
+		}
+	}
+
+	for token in tokens.iter_mut() {
+		match token.token {
+			// a revolution that isn't part of rpm is a turn
+			Token::LexerKeyword(Revolution) => token.token = Token::Unit(Turn),
+			// a per that isn't part of a unit like km/h is a division, like kg per m3
+			Token::LexerKeyword(Per) => token.token = Token::Operator(Divide),
+			_ => {},
+		}
+	}
+
+	// decide if deg and ° are angles or temperatures. "°C" and "deg f" are
+	// temperatures. Otherwise, they're angles if the input has other angle
+	// units or trigonometric functions, and temperatures if it has other
+	// temperature units. If it has both or neither, default_degree is used.
+	let mut token_index = 0;
+	while token_index < tokens.len() {
+		if tokens[token_index].token == Token::LexerKeyword(AmbiguousDegree) {
+			if let Some(Token::Unit(unit)) = tokens.get(token_index + 1).map(|t| &t.token) {
+				if unit.category() == UnitType::Temperature {
+					tokens[token_index + 1].span.start = tokens[token_index].span.start;
+					tokens.remove(token_index);
+				}
+			}
+		}
+		token_index += 1;
+	}
+	let has_angle = tokens.iter().any(|t| match &t.token {
+		Token::Unit(unit) => unit.category() == UnitType::Angle,
+		Token::FunctionIdentifier(function) => matches!(function, Sin | Cos | Tan | Sec | Csc | Cot | Asin | Acos | Atan | Atan2),
+		_ => false,
+	});
+	let has_temperature = tokens.iter().any(|t| match &t.token {
+		Token::Unit(unit) => unit.category() == UnitType::Temperature,
+		_ => false,
+	});
+	let degree = match (has_angle, has_temperature) {
+		(true, false) => Degree,
+		(false, true) if default_degree.category() == UnitType::Temperature => default_degree,
+		(false, true) => Celsius,
+		_ => default_degree,
+	};
+	for token in tokens.iter_mut() {
+		if token.token == Token::LexerKeyword(AmbiguousDegree) {
+			token.token = Token::Unit(degree);
+		}
+	}
+
+	// currencies can be written before the amount, like "$5" or "eur 10 million"
+	let mut token_index = 0;
+	while token_index + 1 < tokens.len() {
+		let is_currency = matches!(tokens[token_index].token, Token::Unit(Unit::Currency(_)));
+		let after_amount = matches!(tokens.get(token_index.wrapping_sub(1)).map(|t| &t.token), Some(Token::Number(_)) | Some(Token::NamedNumber(_)));
+		if is_currency && !after_amount && matches!(tokens[token_index + 1].token, Token::Number(_)) {
+			let mut amount_end = token_index + 2;
+			while matches!(tokens.get(amount_end).map(|t| &t.token), Some(Token::NamedNumber(_))) {
+				amount_end += 1;
+			}
+			tokens[token_index..amount_end].rotate_left(1);
+			token_index = amount_end;
+		} else {
+			token_index += 1;
+		}
+	}
+
+	// only identifiers can be assigned to, so "m = 5" is an error
+	if let (Some(name), Some(Token::Assign)) = (tokens.first(), tokens.get(1).map(|t| &t.token)) {
+		if !matches!(name.token, Token::Identifier(_)) {
+			return Err(Error::ReservedName {
+				name: original_input[name.span.start..name.span.end].to_ascii_lowercase(),
+				span: Some(name.span),
+			});
+		}
+	}
+
 	Ok(lexer.tokens)
 }
 
@@ -938,6 +1938,10 @@ mod tests {
 	use crate::numtok;
 	use regex::Regex;
 
+	fn lex_tokens(input: &str) -> Result<Vec<Token>, Error> {
+		Ok(lex(input, false, Unit::Celsius)?.into_iter().map(|t| t.token).collect())
+	}
+
 	#[test]
 	fn test_lex() {
 		let strip_operator_spacing = Regex::new(r" ([+\-*/]) ").unwrap();
@@ -945,7 +1949,7 @@ mod tests {
 		let nonplural_data_units = Regex::new(r"(bit|byte)s").unwrap();
 
 		let run_lex = |input: &str, expected_tokens: Vec<Token>| {
-			let tokens = match lex(input, false, Unit::Celsius) {
+			let tokens = match lex_tokens(input) {
 				Ok(tokens) => tokens,
 				Err(e) => {
 					panic!("lex error: {}\nrun_lex input: {}", e, input);
@@ -956,17 +1960,17 @@ mod tests {
 
 			// Prove we can handle multiple spaces wherever we handle a single space
 			let input_extra_spaces = input.replace(" ", "   ");
-			let tokens_extra_spaces = lex(&input_extra_spaces, false, Unit::Celsius).unwrap();
+			let tokens_extra_spaces = lex_tokens(&input_extra_spaces).unwrap();
 			assert!(tokens_extra_spaces == expected_tokens, "{}", info_msg);
 
 			// Prove we don't need spaces around operators
 			let input_stripped_spaces = strip_operator_spacing.replace_all(input, "$1");
-			let tokens_stripped_spaces = lex(&input_stripped_spaces, false, Unit::Celsius).unwrap();
+			let tokens_stripped_spaces = lex_tokens(&input_stripped_spaces).unwrap();
 			assert!(tokens_stripped_spaces == expected_tokens, "{}", info_msg);
 
 			// Prove we don't need a space after a digit
 			let input_afterdigit_stripped_spaces = strip_afterdigit_spacing.replace_all(input, "$1");
-			let tokens_afterdigit_stripped_spaces = lex(&input_afterdigit_stripped_spaces, false, Unit::Celsius).unwrap();
+			let tokens_afterdigit_stripped_spaces = lex_tokens(&input_afterdigit_stripped_spaces).unwrap();
 			assert!(tokens_afterdigit_stripped_spaces == expected_tokens, "{}", info_msg);
 		};
 
@@ -975,7 +1979,7 @@ mod tests {
 
 			// Prove plural and non-plural data units behave identically
 			let input_nonplural_units = nonplural_data_units.replace_all(input, "$1");
-			let tokens_nonplural_units = lex(&input_nonplural_units, false, Unit::Celsius).unwrap();
+			let tokens_nonplural_units = lex_tokens(&input_nonplural_units).unwrap();
 			let info_msg = format!("run_datarate_lex input: {}\nexpected: {:?}\nreceived: {:?}", input, expected_tokens, tokens_nonplural_units);
 			assert!(tokens_nonplural_units == expected_tokens, "{}", info_msg);
 		};
@@ -988,6 +1992,58 @@ mod tests {
 		run_lex("1 light year", vec![numtok!(1), Token::Unit(LightYear)]);
 		run_lex("10 lightsec", vec![numtok!(10), Token::Unit(LightSecond)]);
 		run_lex("12 light secs", vec![numtok!(12), Token::Unit(LightSecond)]);
+		run_lex("3 light minutes", vec![numtok!(3), Token::Unit(LightMinute)]);
+		run_lex("1 au", vec![numtok!(1), Token::Unit(AstronomicalUnit)]);
+		run_lex("2 astronomical units", vec![numtok!(2), Token::Unit(AstronomicalUnit)]);
+		run_lex("1.3 pc", vec![numtok!(1.3), Token::Unit(Parsec)]);
+		run_lex("60 earth radii", vec![numtok!(60), Token::Unit(EarthRadius)]);
+		run_lex("500 nm", vec![numtok!(500), Token::Unit(Nanometer)]);
+		run_lex("500 Nm", vec![numtok!(500), Token::Unit(NewtonMeter)]);
+		run_lex("5 µm", vec![numtok!(5), Token::Unit(Micrometer)]);
+		run_lex("5 um", vec![numtok!(5), Token::Unit(Micrometer)]);
+		run_lex("1.5 Å", vec![numtok!(1.5), Token::Unit(Angstrom)]);
+		run_lex("1.5 angstroms", vec![numtok!(1.5), Token::Unit(Angstrom)]);
+		run_lex("3 thou", vec![numtok!(3), Token::Unit(Thou)]);
+		run_lex("2 fathoms", vec![numtok!(2), Token::Unit(Fathom)]);
+		run_lex("8 furlongs", vec![numtok!(8), Token::Unit(Furlong)]);
+
+		run_lex("5 usd", vec![numtok!(5), currency("usd")]);
+		run_lex("$5", vec![numtok!(5), currency("usd")]);
+		run_lex("-€5.50", vec![Token::Operator(Minus), numtok!(5.50), currency("eur")]);
+		run_lex("£3 million + 2 EUR", vec![numtok!(3), Token::NamedNumber(Million), currency("gbp"), Token::Operator(Plus), numtok!(2), currency("eur")]);
+		run_lex("JPY 1,000 to $", vec![numtok!(1000), currency("jpy"), Token::TextOperator(To), currency("usd")]);
+		run_lex("10 euros in dollars", vec![numtok!(10), currency("eur"), Token::TextOperator(To), currency("usd")]);
+
+		run_lex("2026-03-01", vec![numtok!(1772323200), Token::Unit(Date)]);
+		run_lex("2026-03-01T12:00Z - 2026-03-01t06:30:15.5", vec![numtok!(1772366400), Token::Unit(DateTime), Token::Operator(Minus), numtok!(1772346615.5), Token::Unit(DateTime)]);
+		run_lex("today + 6 weeks", vec![Token::Constant(Today), Token::Operator(Plus), numtok!(6), Token::Unit(Week)]);
+		run_lex("now to unix", vec![Token::Constant(Now), Token::TextOperator(To), Token::Unit(UnixTimestamp)]);
+		run_lex("2026-1-1", vec![numtok!(2026), Token::Operator(Minus), numtok!(1), Token::Operator(Minus), numtok!(1)]);
+		assert!(matches!(lex("2026-02-30", false, Unit::Celsius), Err(Error::InvalidDate { .. })));
+
+		run_lex("3pm pst", vec![numtok!(54000), Token::Unit(Second), Token::Unit(Zoned(TimeZone::from_abbreviation("pst").unwrap().unwrap()))]);
+		run_lex("12:30 am", vec![numtok!(1800), Token::Unit(Second)]);
+		run_lex("14:00 europe/berlin", vec![numtok!(50400), Token::Unit(Second), Token::Unit(Zoned(TimeZone::from_name("europe/berlin").unwrap()))]);
+		run_lex("tokyo * 2", vec![Token::Identifier("tokyo".to_string()), Token::Operator(Multiply), numtok!(2)]);
+		run_lex("now in tokyo", vec![Token::Constant(Now), Token::TextOperator(To), Token::Unit(Zoned(TimeZone::from_name("asia/tokyo").unwrap()))]);
+		run_lex("3 pmx", vec![numtok!(3), Token::Identifier("pmx".to_string())]);
+		assert!(matches!(lex("3pm cst", false, Unit::Celsius), Err(Error::AmbiguousTimeZone { span: Some(Span { start: 4, end: 7 }), .. })));
+		assert!(matches!(lex("12:60", false, Unit::Celsius), Err(Error::InvalidTime { .. })));
+		assert!(matches!(lex("13:00 pm", false, Unit::Celsius), Err(Error::InvalidTime { .. })));
+
+		run_lex("1:30:15", vec![numtok!(5415), Token::Unit(Second)]);
+		run_lex("36:15", vec![numtok!(130500), Token::Unit(Second)]);
+		run_lex("1:30:15 + 45:00", vec![numtok!(5415), Token::Unit(Second), Token::Operator(Plus), numtok!(2700), Token::Unit(Second)]);
+		run_lex("1:30:15 + 3:30 pm", vec![numtok!(5415), Token::Unit(Second), Token::Operator(Plus), numtok!(55800), Token::Unit(Second)]);
+		run_lex("1:30.25", vec![numtok!(90.25), Token::Unit(Second)]);
+		run_lex("90:05.5", vec![numtok!(5405.5), Token::Unit(Second)]);
+		run_lex("100 min to h:mm:ss", vec![numtok!(100), Token::Unit(Minute), Token::TextOperator(To), Token::NumberFormat(NumberFormat::Clock)]);
+		run_lex("67 in to ft+in", vec![numtok!(67), Token::Unit(Inch), Token::TextOperator(To), Token::Unit(Foot), Token::Operator(Plus), Token::Unit(Inch)]);
+		run_lex("8000 s to h:min:s", vec![numtok!(8000), Token::Unit(Second), Token::TextOperator(To), Token::Unit(Hour), Token::Operator(Plus), Token::Unit(Minute), Token::Operator(Plus), Token::Unit(Second)]);
+		run_lex("2h 15m 30s", vec![numtok!(2), Token::Unit(Hour), numtok!(15), Token::Unit(Minute), numtok!(30), Token::Unit(Second)]);
+		run_lex("15m 30s", vec![numtok!(15), Token::Unit(Minute), numtok!(30), Token::Unit(Second)]);
+		run_lex("2 h * 15 m", vec![numtok!(2), Token::Unit(Hour), Token::Operator(Multiply), numtok!(15), Token::Unit(Meter)]);
+		run_lex("2h 15 meters", vec![numtok!(2), Token::Unit(Hour), numtok!(15), Token::Unit(Meter)]);
 		run_lex("33.3 square meters", vec![numtok!(33.3), Token::Unit(SquareMeter)]);
 		run_lex("54 m2", vec![numtok!(54), Token::Unit(SquareMeter)]);
 		run_lex("87 sq miles", vec![numtok!(87), Token::Unit(SquareMile)]);
@@ -1010,9 +2066,14 @@ mod tests {
 		run_lex("210 lbs", vec![numtok!(210), Token::Unit(Pound)]);
 		run_lex("210 pound", vec![numtok!(210), Token::Unit(Pound)]);
 		run_lex("210 pounds", vec![numtok!(210), Token::Unit(Pound)]);
-		run_lex("210 pounds-force", vec![numtok!(210), Token::LexerKeyword(PoundForce)]);
+		run_lex("210 pounds-force", vec![numtok!(210), Token::Unit(PoundForce)]);
 		run_lex("3 ton", vec![numtok!(3), Token::Unit(ShortTon)]);
 		run_lex("3 short tons", vec![numtok!(3), Token::Unit(ShortTon)]);
+		run_lex("3 st", vec![numtok!(3), Token::Unit(ShortTon)]);
+		run_lex("13 st 7 lb", vec![numtok!(13), Token::Unit(Stone), numtok!(7), Token::Unit(Pound)]);
+		run_lex("2 st to stone", vec![numtok!(2), Token::Unit(Stone), Token::TextOperator(To), Token::Unit(Stone)]);
+		run_lex("13.5 st to st+lb", vec![numtok!(13.5), Token::Unit(Stone), Token::TextOperator(To), Token::Unit(Stone), Token::Operator(Plus), Token::Unit(Pound)]);
+		run_lex("200 lb to st+lb", vec![numtok!(200), Token::Unit(Pound), Token::TextOperator(To), Token::Unit(Stone), Token::Operator(Plus), Token::Unit(Pound)]);
 		run_lex("4 lt", vec![numtok!(4), Token::Unit(LongTon)]);
 		run_lex("4 long tonnes", vec![numtok!(4), Token::Unit(LongTon)]);
 		run_datarate_lex("1 bit", vec![numtok!(1), Token::Unit(Bit)]);
@@ -1174,7 +2235,31 @@ mod tests {
 		run_lex("46 british thermal units", vec![numtok!(46), Token::Unit(BritishThermalUnit)]);
 		run_lex("5432 newton metres", vec![numtok!(5432), Token::Unit(NewtonMeter)]);
 		run_lex("2345 newton-meters", vec![numtok!(2345), Token::Unit(NewtonMeter)]);
-		run_lex("20 lbf", vec![numtok!(20), Token::LexerKeyword(PoundForce)]);
+		run_lex("20 lbf", vec![numtok!(20), Token::Unit(PoundForce)]);
+		run_lex("10 l/min", vec![numtok!(10), Token::Unit(LitersPerMinute)]);
+		run_lex("2 m3 per hour", vec![numtok!(2), Token::Unit(CubicMetersPerHour)]);
+		run_lex("300 gpm", vec![numtok!(300), Token::Unit(GallonsPerMinute)]);
+		run_lex("5 kg/h", vec![numtok!(5), Token::Unit(KilogramsPerHour)]);
+		run_lex("7.8 g/cm³", vec![numtok!(7.8), Token::Unit(GramsPerCubicCentimeter)]);
+		run_lex("62 lb/ft3", vec![numtok!(62), Token::Unit(PoundsPerCubicFoot)]);
+		run_lex("6 l/100km", vec![numtok!(6), Token::Unit(LitersPer100Kilometers)]);
+		run_lex("6 liters per 100 km", vec![numtok!(6), Token::Unit(LitersPer100Kilometers)]);
+		run_lex("30 mpg to l/100 km", vec![numtok!(30), Token::Unit(MilesPerGallon), Token::TextOperator(To), Token::Unit(LitersPer100Kilometers)]);
+		run_lex("20 km/l", vec![numtok!(20), Token::Unit(KilometersPerLiter)]);
+		run_lex("3000 mAh", vec![numtok!(3000), Token::Unit(MilliampereHour)]);
+		run_lex("100 µF", vec![numtok!(100), Token::Unit(Microfarad)]);
+		run_lex("100 uF", vec![numtok!(100), Token::Unit(Microfarad)]);
+		run_lex("47 nF", vec![numtok!(47), Token::Unit(Nanofarad)]);
+		run_lex("10 mH", vec![numtok!(10), Token::Unit(Millihenry)]);
+		run_lex("2 siemens", vec![numtok!(2), Token::Unit(Siemens)]);
+		run_lex("1 Wb", vec![numtok!(1), Token::Unit(Weber)]);
+		run_lex("50 µT", vec![numtok!(50), Token::Unit(Microtesla)]);
+		run_lex("5 kg force", vec![numtok!(5), Token::Unit(KilogramForce)]);
+		run_lex("1 kilogram-force", vec![numtok!(1), Token::Unit(KilogramForce)]);
+		run_lex("2 pounds force", vec![numtok!(2), Token::Unit(PoundForce)]);
+		run_lex("3 newton", vec![numtok!(3), Token::Unit(Newton)]);
+		run_lex("3 newton * 2 m", vec![numtok!(3), Token::Unit(Newton), Token::Operator(Multiply), numtok!(2), Token::Unit(Meter)]);
+		run_lex("7 N to dyn", vec![numtok!(7), Token::Unit(Newton), Token::TextOperator(To), Token::Unit(Dyne)]);
 		run_lex("60 hz", vec![numtok!(60), Token::Unit(Hertz)]);
 		run_lex("1100 rpm", vec![numtok!(1100), Token::Unit(RevolutionsPerMinute)]);
 		run_lex("1150 revolutions per minute", vec![numtok!(1150), Token::Unit(RevolutionsPerMinute)]);
@@ -1206,6 +2291,13 @@ mod tests {
 		run_lex("30 pa", vec![numtok!(30), Token::Unit(Pascal)]);
 		run_lex("23 celsius + 4 celsius", vec![numtok!(23), Token::Unit(Celsius), Token::Operator(Plus), numtok!(4), Token::Unit(Celsius)]);
 		run_lex("54 f - 1.5 fahrenheit", vec![numtok!(54), Token::Unit(Fahrenheit), Token::Operator(Minus), numtok!(1.5), Token::Unit(Fahrenheit)]);
+		run_lex("25 °C", vec![numtok!(25), Token::Unit(Celsius)]);
+		run_lex("30 deg f", vec![numtok!(30), Token::Unit(Fahrenheit)]);
+		run_lex("20 deg", vec![numtok!(20), Token::Unit(Celsius)]);
+		run_lex("20 deg to f", vec![numtok!(20), Token::Unit(Celsius), Token::TextOperator(To), Token::Unit(Fahrenheit)]);
+		run_lex("sin(90 deg)", vec![Token::FunctionIdentifier(Sin), Token::Operator(LeftParen), numtok!(90), Token::Unit(Degree), Token::Operator(RightParen)]);
+		run_lex("180° in rad", vec![numtok!(180), Token::Unit(Degree), Token::TextOperator(To), Token::Unit(Radian)]);
+		run_lex("1 rev to arcmin", vec![numtok!(1), Token::Unit(Turn), Token::TextOperator(To), Token::Unit(Arcminute)]);
 		run_lex("50 metric tonnes", vec![numtok!(50), Token::Unit(MetricTon)]);
 		run_lex("77 metric hps", vec![numtok!(77), Token::Unit(MetricHorsepower)]);
 
@@ -1227,6 +2319,58 @@ mod tests {
 		run_lex("12 pound+", vec![numtok!(12), Token::Unit(Pound), Token::Operator(Plus)]);
 
 		run_lex("5 π m", vec![numtok!(5), Token::Constant(Pi), Token::Unit(Meter)]);
+		run_lex("6.022e23", vec![numtok!(6.022e23)]);
+		run_lex("0xff + 0o755 + 0b1010", vec![numtok!(255), Token::Operator(Plus), numtok!(493), Token::Operator(Plus), numtok!(10)]);
+		run_lex("0b", vec![numtok!(0), Token::Identifier("b".to_string())]);
+		run_lex("0xff and not 0x0f", vec![numtok!(255), Token::Operator(BitAnd), Token::Operator(BitNot), numtok!(15)]);
+		run_lex("1 or 2 xor 3", vec![numtok!(1), Token::Operator(BitOr), numtok!(2), Token::Operator(BitXor), numtok!(3)]);
+		run_lex("1 KiB << 10 >> 2", vec![numtok!(1), Token::Unit(Kibibyte), Token::Operator(ShiftLeft), numtok!(10), Token::Operator(ShiftRight), numtok!(2)]);
+		run_lex("255 to hex", vec![numtok!(255), Token::TextOperator(To), Token::NumberFormat(NumberFormat::Base(16))]);
+		run_lex("255 in base 36", vec![numtok!(255), Token::TextOperator(To), Token::NumberFormat(NumberFormat::Base(36))]);
+		run_lex("1.6E-19 C", vec![numtok!(1.6e-19), Token::Unit(Coulomb)]);
+		run_lex("1.6e-19 c", vec![numtok!(1.6e-19), Token::Unit(Celsius)]);
+		run_lex("25 C", vec![numtok!(25), Token::Unit(Celsius)]);
+		run_lex("0xe C", vec![numtok!(14), Token::Unit(Celsius)]);
+		run_lex("3e+8 m/s", vec![numtok!(3e8), Token::Unit(MetersPerSecond)]);
+		run_lex("2e", vec![numtok!(2), Token::Constant(E)]);
+		run_lex("2e-x", vec![numtok!(2), Token::Constant(E), Token::Operator(Minus), Token::Identifier("x".to_string())]);
+		run_lex("ans * 2", vec![Token::Ans, Token::Operator(Multiply), numtok!(2)]);
+		run_lex("_ km", vec![Token::Ans, Token::Unit(Kilometer)]);
+		run_lex("speed = x / 20 min", vec![Token::Identifier("speed".to_string()), Token::Assign, Token::Identifier("x".to_string()), Token::Operator(Divide), numtok!(20), Token::Unit(Minute)]);
 		run_lex("5 Ω + 2 mΩ", vec![numtok!(5), Token::Unit(Ohm), Token::Operator(Plus), numtok!(2), Token::Unit(Milliohm)]);
+		run_lex("1,000,000", vec![numtok!(1000000)]);
+		run_lex("atan2(1, 2)", vec![Token::FunctionIdentifier(Atan2), Token::Operator(LeftParen), numtok!(1), Token::Operator(Comma), numtok!(2), Token::Operator(RightParen)]);
+		run_lex("atan2(1,20)", vec![Token::FunctionIdentifier(Atan2), Token::Operator(LeftParen), numtok!(1), Token::Operator(Comma), numtok!(20), Token::Operator(RightParen)]);
+		run_lex("sec(0)", vec![Token::FunctionIdentifier(Sec), Token::Operator(LeftParen), numtok!(0), Token::Operator(RightParen)]);
+		run_lex("5 sec", vec![numtok!(5), Token::Unit(Second)]);
+		run_lex("max(1,500)", vec![Token::FunctionIdentifier(Max), Token::Operator(LeftParen), numtok!(1), Token::Operator(Comma), numtok!(500), Token::Operator(RightParen)]);
+		assert!(matches!(lex("max(1, 2,000)", false, Unit::Celsius), Err(Error::AmbiguousComma { span: Some(Span { start: 8, end: 9 }) })));
+		assert!(matches!(lex("1,5", false, Unit::Celsius), Err(Error::InvalidNumber { span: Some(Span { start: 0, end: 3 }), .. })));
+		assert!(matches!(lex("1,000,00", false, Unit::Celsius), Err(Error::InvalidNumber { .. })));
+		run_lex("(1,500)", vec![Token::Operator(LeftParen), numtok!(1500), Token::Operator(RightParen)]);
+		run_lex("min(1, 2)", vec![Token::FunctionIdentifier(Min), Token::Operator(LeftParen), numtok!(1), Token::Operator(Comma), numtok!(2), Token::Operator(RightParen)]);
+		run_lex("5 min", vec![numtok!(5), Token::Unit(Minute)]);
+		run_lex("arsinh(1)", vec![Token::FunctionIdentifier(Asinh), Token::Operator(LeftParen), numtok!(1), Token::Operator(RightParen)]);
+		run_lex("kg*km/h^2", vec![Token::Unit(Kilogram), Token::Operator(Multiply), Token::Unit(Kilometer), Token::Operator(Divide), Token::Unit(Hour), Token::Operator(Caret), numtok!(2)]);
+		run_lex("kg*m/s^2", vec![Token::Unit(Kilogram), Token::Operator(Multiply), Token::Unit(MetersPerSecondSquared)]);
+		run_lex("9.81 m/s²", vec![numtok!(9.81), Token::Unit(MetersPerSecondSquared)]);
+		run_lex("32 ft per s^2", vec![numtok!(32), Token::Unit(FeetPerSecondSquared)]);
+		run_lex("1 km/s²", vec![numtok!(1), Token::Unit(Kilometer), Token::Operator(Divide), Token::Unit(Second), Token::Operator(Caret), numtok!(2)]);
+		run_lex("2 g0", vec![numtok!(2), Token::Unit(StandardGravity)]);
+		run_lex("2 g", vec![numtok!(2), Token::Unit(Gram)]);
+		run_lex("5 mg per m3", vec![numtok!(5), Token::Unit(Milligram), Token::Operator(Divide), Token::Unit(CubicMeter)]);
+		run_lex("g·cm", vec![Token::Unit(Gram), Token::Operator(Multiply), Token::Unit(Centimeter)]);
+
+		// Spans point at the original input, commas included
+		let spans: Vec<Span> = lex("1,000 km/h + 5", false, Unit::Celsius).unwrap().into_iter().map(|t| t.span).collect();
+		assert_eq!(spans, vec![Span::new(0, 5), Span::new(6, 10), Span::new(11, 12), Span::new(13, 14)]);
+		let spans: Vec<Span> = lex("3 Pounds-5", false, Unit::Celsius).unwrap().into_iter().map(|t| t.span).collect();
+		assert_eq!(spans, vec![Span::new(0, 1), Span::new(2, 8), Span::new(8, 9), Span::new(9, 10)]);
+		let error = lex("1,000 + 5 @", false, Unit::Celsius).unwrap_err();
+		assert_eq!(error, Error::InvalidCharacter { character: "@".to_string(), span: Some(Span::new(10, 11)) });
+		let error = lex("S = 5", false, Unit::Celsius).unwrap_err();
+		assert_eq!(error, Error::ReservedName { name: "s".to_string(), span: Some(Span::new(0, 1)) });
+		let error = lex("1,000 nautical foo", false, Unit::Celsius).unwrap_err();
+		assert_eq!(error, Error::UnknownWord { word: "foo".to_string(), span: Some(Span::new(6, 18)) });
 	}
 }
@@ -1,10 +1,7 @@
-#![cfg_attr(
-	feature = "cargo-clippy",
-	allow(
-		clippy::comparison_chain,
-		clippy::if_same_then_else,
-		clippy::match_like_matches_macro,
-	)
+#![allow(
+	clippy::comparison_chain,
+	clippy::if_same_then_else,
+	clippy::match_like_matches_macro
 )]
 //! calculation + conversion
 //!
@@ -30,13 +27,26 @@
 //! }
 //! ```
 
-use crate::units::Unit;
+pub use crate::currency::{set_exchange_rates, ExchangeRates};
+pub use crate::error::{Error, Span};
+use crate::units::{Unit, UnitType};
 use decimal::d128;
+use std::collections::HashMap;
 use std::fmt::{self, Display};
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 
+/// Currencies, and the exchange rates used to convert between them
+pub mod currency;
+/// Dates and times, and calendar arithmetic with them
+pub mod datetime;
+/// Dimensional analysis of units, like length × length = area
+pub mod dimensions;
+/// The [`Error`] type returned by [`eval`]
+pub mod error;
 /// Turns an [`AstNode`](parser::AstNode) into a [`Number`]
 pub mod evaluator;
+/// Time zones, used to show dates and times in local time
+pub mod timezone;
 /// Turns a string into [`Token`]s
 #[rustfmt::skip]
 pub mod lexer;
@@ -57,33 +67,134 @@ pub mod units;
 /// use cpc::units::Unit;
 /// use decimal::d128;
 ///
-/// let x = Number {
-///   value: d128!(100),
-///   unit: Unit::Meter,
-/// };
+/// let x = Number::new(d128!(100), Unit::Meter);
+/// assert_eq!(x.to_string(), "100 Meter");
 /// ```
 pub struct Number {
 	/// The number part of a [`Number`] struct
 	pub value: d128,
 	/// The unit of a [`Number`] struct. This can be [`NoType`](units::UnitType::NoType)
 	pub unit: Unit,
+	/// How the value is displayed, for example in hexadecimal after `to hex`
+	pub format: NumberFormat,
 }
 
 impl Number {
 	pub const fn new(value: d128, unit: Unit) -> Number {
-		Number { value, unit }
+		Number {
+			value,
+			unit,
+			format: NumberFormat::Decimal,
+		}
 	}
 }
 impl Display for Number {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if let NumberFormat::Mixed(mixed) = self.format {
+			if let Ok(parts) = units::decompose(self.clone(), &mixed.units()) {
+				let sign = if self.value.is_negative() { "-" } else { "" };
+				let parts: Vec<String> = parts
+					.iter()
+					.map(|part| Number::new(part.value.abs(), part.unit).to_string())
+					.collect();
+				return write!(f, "{}{}", sign, parts.join(" "));
+			}
+		}
 		// 0.2/0.01 results in 2E+1, but if we add zero it becomes 20
 		let fixed_value = self.value + d128!(0);
+		let formatted_value = match self.format {
+			NumberFormat::Decimal | NumberFormat::Mixed(_) => format!("{}", fixed_value),
+			NumberFormat::Base(base) => match to_integer(fixed_value) {
+				Some(integer) if integer >= 0 => to_base_string(integer as u128, base),
+				_ => format!("{}", fixed_value),
+			},
+			NumberFormat::Clock => match self.unit.category() {
+				UnitType::Time => datetime::format_clock(units::to_si(self)),
+				_ => format!("{}", fixed_value),
+			},
+		};
 		let output = match self.unit {
-			Unit::NoUnit => format!("{}", fixed_value),
-			unit => format!("{} {:?}", fixed_value, unit),
+			_ if self.format == NumberFormat::Clock && self.unit.category() == UnitType::Time => {
+				formatted_value
+			}
+			Unit::NoUnit => formatted_value,
+			Unit::Composite(composite) => format!("{} {}", formatted_value, composite),
+			Unit::Currency(currency) => format!("{} {}", formatted_value, currency),
+			Unit::Date | Unit::DateTime | Unit::Zoned(_) => {
+				match datetime::format(self.value, self.unit) {
+					Some(datetime) => datetime,
+					None => format!("{} {:?}", formatted_value, self.unit),
+				}
+			}
+			unit => format!("{} {:?}", formatted_value, unit),
 		};
-		write!(f, "{}", output)
+		match self.format {
+			NumberFormat::Base(2) | NumberFormat::Base(8) | NumberFormat::Base(16) => {
+				write!(f, "{}", output)
+			}
+			NumberFormat::Base(base) => write!(f, "{} (base {})", output, base),
+			NumberFormat::Decimal | NumberFormat::Clock | NumberFormat::Mixed(_) => {
+				write!(f, "{}", output)
+			}
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How the value of a [`Number`] is displayed
+pub enum NumberFormat {
+	/// Normal decimal numbers, like `255`
+	Decimal,
+	/// An integer in a base from 2 to 36. Binary, octal and hexadecimal get a
+	/// prefix, like `0b11111111`, `0o377` and `0xff`.
+	Base(u32),
+	/// A [`Time`](units::UnitType::Time) in hours, minutes and seconds, like
+	/// `1:30:15`
+	Clock,
+	/// Split into several units, like `5 Foot 7.3 Inch`. See
+	/// [`units::decompose`].
+	Mixed(units::MixedUnits),
+}
+
+/// Returns the value of a [`struct@d128`] as an [`i128`], if it's an integer
+/// that fits
+pub(crate) fn to_integer(value: d128) -> Option<i128> {
+	let integer = value.quantize(d128!(1));
+	if integer.is_nan() || integer != value {
+		return None;
 	}
+	integer.to_string().parse().ok()
+}
+
+/// Rounds a [`struct@d128`] down to an integer
+pub(crate) fn floor(value: d128) -> d128 {
+	let integer = value.quantize(d128!(1));
+	if integer > value {
+		integer - d128!(1)
+	} else {
+		integer
+	}
+}
+
+/// Formats an integer in a base from 2 to 36
+fn to_base_string(mut integer: u128, base: u32) -> String {
+	let prefix = match base {
+		2 => "0b",
+		8 => "0o",
+		16 => "0x",
+		_ => "",
+	};
+	let mut digits = Vec::new();
+	loop {
+		let digit = (integer % base as u128) as u32;
+		digits.push(std::char::from_digit(digit, base).unwrap());
+		integer /= base as u128;
+		if integer == 0 {
+			break;
+		}
+	}
+	let digits: String = digits.iter().rev().collect();
+	format!("{}{}", prefix, digits)
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -95,6 +206,20 @@ pub enum Operator {
 	Divide,
 	Modulo,
 	Caret,
+	/// Bitwise `and`
+	BitAnd,
+	/// Bitwise `or`
+	BitOr,
+	/// Bitwise `xor`
+	BitXor,
+	/// Bitwise `not`, which only has a right side
+	BitNot,
+	/// `<<`
+	ShiftLeft,
+	/// `>>`
+	ShiftRight,
+	/// The `,` between function arguments
+	Comma,
 	LeftParen,  // lexer only
 	RightParen, // lexer only
 }
@@ -147,6 +272,10 @@ pub enum NamedNumber {
 pub enum Constant {
 	Pi,
 	E,
+	/// The current date and time, from [`Context::now`]
+	Now,
+	/// The current date, from [`Context::now`]
+	Today,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -158,15 +287,51 @@ pub enum FunctionIdentifier {
 	Log,
 	Ln,
 	Exp,
+	Root,
 
 	Round,
 	Ceil,
 	Floor,
 	Abs,
 
+	Min,
+	Max,
+	Clamp,
+	Hypot,
+
 	Sin,
 	Cos,
 	Tan,
+	Sec,
+	Csc,
+	Cot,
+	Asin,
+	Acos,
+	Atan,
+	Atan2,
+
+	Sinh,
+	Cosh,
+	Tanh,
+	Asinh,
+	Acosh,
+	Atanh,
+}
+
+impl FunctionIdentifier {
+	/// The minimum and maximum number of arguments this function takes.
+	/// A maximum of `None` means there is no limit.
+	pub fn arity(&self) -> (usize, Option<usize>) {
+		match self {
+			FunctionIdentifier::Log => (1, Some(2)),
+			FunctionIdentifier::Atan2 | FunctionIdentifier::Root => (2, Some(2)),
+			FunctionIdentifier::Clamp => (3, Some(3)),
+			FunctionIdentifier::Min | FunctionIdentifier::Max | FunctionIdentifier::Hypot => {
+				(1, None)
+			}
+			_ => (1, Some(1)),
+		}
+	}
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -184,9 +349,10 @@ pub enum LexerKeyword {
 	DoubleQuotes,
 	Mercury,
 	Hg,
-	PoundForce,
 	Force,
 	Revolution,
+	/// `deg` or `°`, which can be an angle or a temperature
+	AmbiguousDegree,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -210,6 +376,21 @@ pub enum Token {
 	/// The `-` symbol, specifically when used as `-5` and not `5-5`. Used by the parser only
 	Negative,
 	Unit(units::Unit),
+	/// The previous answer, written as `ans` or `_`
+	Ans,
+	/// A variable name, like `x`
+	Identifier(String),
+	/// A format to convert to, like the `hex` in `255 to hex`
+	NumberFormat(NumberFormat),
+	/// The `=` in an assignment, like `x = 5 km`
+	Assign,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A [`Token`] along with the [`Span`] of the input it was lexed from
+pub struct SpannedToken {
+	pub token: Token,
+	pub span: Span,
 }
 
 #[macro_export]
@@ -219,6 +400,33 @@ macro_rules! numtok {
 	};
 }
 
+#[derive(Clone, Debug, Default)]
+/// State that is kept between evaluations, for example in the REPL.
+///
+/// Example:
+/// ```rust
+/// use cpc::{eval_with_context, Context};
+/// use cpc::units::Unit;
+///
+/// let mut context = Context::default();
+/// eval_with_context("x = 5 km", &mut context, true, Unit::Celsius, false).unwrap();
+/// eval_with_context("speed = x / 20 min", &mut context, true, Unit::Celsius, false).unwrap();
+/// let answer = eval_with_context("speed to mph", &mut context, true, Unit::Celsius, false).unwrap();
+/// assert_eq!(answer.unit, Unit::MilesPerHour);
+/// let answer = eval_with_context("ans * 2", &mut context, true, Unit::Celsius, false).unwrap();
+/// assert_eq!(answer.unit, Unit::MilesPerHour);
+/// ```
+pub struct Context {
+	/// The previous answer, which can be used as `ans` or `_`
+	pub ans: Option<Number>,
+	/// Variables assigned with `name = value`. Names are lowercase, since
+	/// input is case insensitive.
+	pub variables: HashMap<String, Number>,
+	/// The time used for `now` and `today`. If it's `None`, the system clock
+	/// is used, so setting it makes results deterministic.
+	pub now: Option<SystemTime>,
+}
+
 /// Evaluates a string into a resulting [`Number`].
 ///
 /// Example:
@@ -241,48 +449,79 @@ pub fn eval(
 	allow_trailing_operators: bool,
 	default_degree: Unit,
 	verbose: bool,
-) -> Result<Number, String> {
-	let lex_start = Instant::now();
-
-	match lexer::lex(input, allow_trailing_operators, default_degree) {
-		Ok(tokens) => {
-			let lex_time = Instant::now().duration_since(lex_start).as_nanos() as f32;
-			if verbose {
-				println!("Lexed TokenVector: {:?}", tokens);
-			}
+) -> Result<Number, Error> {
+	let mut context = Context::default();
+	eval_with_context(
+		input,
+		&mut context,
+		allow_trailing_operators,
+		default_degree,
+		verbose,
+	)
+}
 
-			let parse_start = Instant::now();
-			match parser::parse(&tokens) {
-				Ok(ast) => {
-					let parse_time = Instant::now().duration_since(parse_start).as_nanos() as f32;
-					if verbose {
-						println!("Parsed AstNode: {:#?}", ast);
-					}
-
-					let eval_start = Instant::now();
-					match evaluator::evaluate(&ast) {
-						Ok(answer) => {
-							let eval_time =
-								Instant::now().duration_since(eval_start).as_nanos() as f32;
-
-							if verbose {
-								println!("Evaluated value: {} {:?}", answer.value, answer.unit);
-								println!("\u{23f1}  {:.3}ms lexing", lex_time / 1000.0 / 1000.0);
-								println!("\u{23f1}  {:.3}ms parsing", parse_time / 1000.0 / 1000.0);
-								println!(
-									"\u{23f1}  {:.3}ms evaluation",
-									eval_time / 1000.0 / 1000.0
-								);
-							}
-
-							Ok(answer)
-						}
-						Err(e) => Err(format!("Eval error: {}", e)),
-					}
-				}
-				Err(e) => Err(format!("Parsing error: {}", e)),
+/// Returns [`Error::UnknownWord`] if a name that isn't a variable comes right
+/// after a number, like `kohm` in `1 kohm`. That's most likely a misspelled
+/// unit, while a name on its own is reported as an undefined variable.
+fn check_unknown_words(tokens: &[SpannedToken], context: &Context) -> Result<(), Error> {
+	for pair in tokens.windows(2) {
+		if let (Token::Number(_), Token::Identifier(name)) = (&pair[0].token, &pair[1].token) {
+			if !context.variables.contains_key(name) {
+				return Err(Error::UnknownWord {
+					word: name.clone(),
+					span: Some(pair[1].span),
+				});
 			}
 		}
-		Err(e) => Err(format!("Lexing error: {}", e)),
 	}
+	Ok(())
+}
+
+/// Evaluates a string into a resulting [`Number`] using a [`Context`].
+///
+/// On success, the answer is stored in [`Context::ans`].
+pub fn eval_with_context(
+	input: &str,
+	context: &mut Context,
+	allow_trailing_operators: bool,
+	default_degree: Unit,
+	verbose: bool,
+) -> Result<Number, Error> {
+	let lex_start = Instant::now();
+	let tokens = lexer::lex(input, allow_trailing_operators, default_degree)?;
+	check_unknown_words(&tokens, context)?;
+	let lex_time = Instant::now().duration_since(lex_start).as_nanos() as f32;
+	if verbose {
+		println!("Lexed TokenVector: {:?}", tokens);
+	}
+
+	let parse_start = Instant::now();
+	let ast = parser::parse(&tokens)?;
+	let parse_time = Instant::now().duration_since(parse_start).as_nanos() as f32;
+	if verbose {
+		println!("Parsed AstNode: {:#?}", ast);
+	}
+
+	let eval_start = Instant::now();
+	let mut answer = evaluator::evaluate_with_context(&ast, context)?;
+	// times calculated from times like "1:30:15 + 45:00" are shown like times,
+	// unless they're converted with "to"
+	if lexer::has_clock_time(&tokens)
+		&& answer.unit.category() == UnitType::Time
+		&& answer.format == NumberFormat::Decimal
+		&& ast.token != Token::TextOperator(TextOperator::To)
+	{
+		answer.format = NumberFormat::Clock;
+	}
+	let eval_time = Instant::now().duration_since(eval_start).as_nanos() as f32;
+
+	if verbose {
+		println!("Evaluated value: {} {:?}", answer.value, answer.unit);
+		println!("\u{23f1}  {:.3}ms lexing", lex_time / 1000.0 / 1000.0);
+		println!("\u{23f1}  {:.3}ms parsing", parse_time / 1000.0 / 1000.0);
+		println!("\u{23f1}  {:.3}ms evaluation", eval_time / 1000.0 / 1000.0);
+	}
+
+	context.ans = Some(answer.clone());
+	Ok(answer)
 }
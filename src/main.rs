@@ -1,21 +1,92 @@
-use cpc::eval;
+use cpc::currency::exchange_rates;
 use cpc::units::Unit;
+use cpc::{eval, set_exchange_rates, Error, ExchangeRates, Number};
 use std::env;
+use std::path::PathBuf;
 use std::process::exit;
+use std::time::Duration;
+
+mod repl;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn print_help() {
 	println!(concat!(
 		"Usage: cpc '<expression>' [options]",
+		"\n       cpc [--repl] [options]",
+		"\n",
+		"\nWithout an expression, cpc starts an interactive session.",
 		"\n",
 		"\nOptions:",
-		"\n    --verbose   Enable verbose logging",
-		"\n    --version   Show cpc version",
-		"\n    --help      Show this help page",
+		"\n    --repl           Start an interactive session",
+		"\n    --rates <file>   Load exchange rates from a JSON file",
+		"\n    --verbose        Enable verbose logging",
+		"\n    --version        Show cpc version",
+		"\n    --help           Show this help page",
+		"\n",
+		"\nExchange rates are loaded from ~/.local/share/cpc/rates.json on Linux,",
+		"\nor the equivalent on other platforms, unless --rates is used.",
 	));
 }
 
+/// Format a duration in whole minutes, hours or days, like `3 days`
+fn format_age(age: Duration) -> String {
+	let minutes = age.as_secs() / 60;
+	let (amount, unit) = if minutes < 60 {
+		(minutes, "minute")
+	} else if minutes < 60 * 24 {
+		(minutes / 60, "hour")
+	} else {
+		(minutes / 60 / 24, "day")
+	};
+	format!("{} {}{}", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+/// Print an answer. Currencies are followed by how old the exchange rates are.
+fn print_answer(answer: &Number) {
+	println!("{}", answer);
+	if let (Unit::Currency(_), Some(rates)) = (answer.unit, exchange_rates()) {
+		eprintln!("Exchange rates are {} old", format_age(rates.age()));
+	}
+}
+
+/// Print an error, underlining the part of the input it's about
+fn print_error(input: &str, error: &Error) {
+	eprintln!("{}", error);
+	if let Some(span) = error.span() {
+		let padding = input[..span.start].chars().count();
+		let length = input[span.start..span.end].chars().count().max(1);
+		eprintln!("  {}", input);
+		eprintln!("  {}{}", " ".repeat(padding), "^".repeat(length));
+	}
+}
+
+/// The default exchange rates file, like `~/.local/share/cpc/rates.json` on Linux
+fn default_rates_path() -> Option<PathBuf> {
+	dirs::data_dir().map(|dir| dir.join("cpc").join("rates.json"))
+}
+
+/// Load exchange rates from `path`, or from the default file if it exists.
+/// Exits if `path` can't be loaded.
+fn load_exchange_rates(path: Option<PathBuf>) {
+	let (path, required) = match path {
+		Some(path) => (path, true),
+		None => match default_rates_path() {
+			Some(path) if path.exists() => (path, false),
+			_ => return,
+		},
+	};
+	match ExchangeRates::load(&path) {
+		Ok(rates) => set_exchange_rates(rates),
+		Err(e) => {
+			eprintln!("{}", e);
+			if required {
+				exit(1);
+			}
+		}
+	}
+}
+
 fn get_args() -> env::Args {
 	let mut args = env::args();
 	args.next(); // skip binary name
@@ -39,10 +110,21 @@ fn main() {
 		}
 	}
 	let mut verbose = false;
+	let mut repl = false;
+	let mut rates_path = None;
 	let mut expression_opt = None;
-	for arg in get_args() {
+	let mut args = get_args();
+	while let Some(arg) = args.next() {
 		match arg.as_str() {
 			"-v" | "--verbose" => verbose = true,
+			"--repl" => repl = true,
+			"--rates" => match args.next() {
+				Some(path) => rates_path = Some(PathBuf::from(path)),
+				None => {
+					eprintln!("--rates needs a file");
+					exit(1);
+				}
+			},
 			_ => {
 				if expression_opt.is_none() {
 					expression_opt = Some(arg);
@@ -53,10 +135,11 @@ fn main() {
 			}
 		}
 	}
+	load_exchange_rates(rates_path);
 	let expression = match expression_opt {
-		Some(expression) => expression,
-		None => {
-			print_help();
+		Some(expression) if !repl => expression,
+		_ => {
+			repl::run(verbose);
 			exit(0);
 		}
 	};
@@ -64,11 +147,11 @@ fn main() {
 	match eval(&expression, true, Unit::Celsius, verbose) {
 		Ok(answer) => {
 			if !verbose {
-				println!("{}", answer);
+				print_answer(&answer);
 			}
 		}
 		Err(e) => {
-			eprintln!("{}", e);
+			print_error(&expression, &e);
 			exit(1);
 		}
 	}
@@ -1,8 +1,11 @@
-use crate::units::Unit::{Foot, Inch};
-use crate::Operator::{Caret, Divide, LeftParen, Minus, Modulo, Multiply, Plus, RightParen};
+use crate::units::Unit;
+use crate::Operator::{
+	BitAnd, BitNot, BitOr, BitXor, Caret, Comma, Divide, LeftParen, Minus, Modulo, Multiply, Plus,
+	RightParen, ShiftLeft, ShiftRight,
+};
 use crate::TextOperator::{Of, To};
-use crate::Token;
 use crate::UnaryOperator::{Factorial, Percent};
+use crate::{Error, Operator, Span, SpannedToken, Token};
 
 #[derive(Debug)]
 /// A struct with a [`Token`](AstNode::token) and [`AstNode`] [`children`](AstNode::children)
@@ -11,45 +14,89 @@ pub struct AstNode {
 	pub children: Vec<AstNode>,
 	/// The token of the [`AstNode`]
 	pub token: Token,
+	/// The [`Span`] of the input this [`AstNode`] and its children were parsed from
+	pub span: Span,
 }
 
 impl AstNode {
-	pub const fn new(token: Token) -> AstNode {
+	pub const fn new(token: Token, span: Span) -> AstNode {
 		AstNode {
 			children: Vec::new(),
 			token,
+			span,
 		}
 	}
 }
 
-/// Parse [`Token`]s into an Abstract Syntax Tree ([`AstNode`])
-pub fn parse(tokens: &[Token]) -> Result<AstNode, String> {
-	parse_level_1(tokens, 0).and_then(|(ast, next_pos)| {
+/// Returns the [`Token`] at `pos`
+fn token_at(tokens: &[SpannedToken], pos: usize) -> Option<&Token> {
+	tokens.get(pos).map(|t| &t.token)
+}
+
+/// Returns the [`Span`] of the token at `pos`, or an empty [`Span`] at the end
+/// of the input if `pos` is past the last token
+fn span_at(tokens: &[SpannedToken], pos: usize) -> Span {
+	match tokens.get(pos) {
+		Some(t) => t.span,
+		None => {
+			let end = tokens.last().map_or(0, |t| t.span.end);
+			Span::new(end, end)
+		}
+	}
+}
+
+/// An [`Error::UnexpectedToken`] for the token at `pos`
+fn unexpected_token(tokens: &[SpannedToken], pos: usize, expected: &'static str) -> Error {
+	Error::UnexpectedToken {
+		token: token_at(tokens, pos).cloned(),
+		expected,
+		span: Some(span_at(tokens, pos)),
+	}
+}
+
+/// Parse [`SpannedToken`]s into an Abstract Syntax Tree ([`AstNode`])
+pub fn parse(tokens: &[SpannedToken]) -> Result<AstNode, Error> {
+	parse_assignment(tokens, 0).and_then(|(ast, next_pos)| {
 		if next_pos == tokens.len() {
 			Ok(ast)
 		} else {
-			Err(format!(
-				"Expected end of input, found {:?} at {}",
-				tokens[next_pos], next_pos
-			))
+			Err(unexpected_token(tokens, next_pos, "end of input"))
 		}
 	})
 }
 
+// assignment: x = 5 km
+/// Parse [`Assign`](Token::Assign). The [`AstNode`] has the
+/// [`Identifier`](Token::Identifier) and the value as its children.
+pub fn parse_assignment(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
+	let token = token_at(tokens, pos);
+	if let (Some(Token::Identifier(_)), Some(Token::Assign)) = (token, token_at(tokens, pos + 1)) {
+		let identifier_node = AstNode::new(token.unwrap().clone(), span_at(tokens, pos));
+		let (value_node, next_pos) = parse_level_1(tokens, pos + 2)?;
+		let span = identifier_node.span.to(value_node.span);
+		let mut new_node = AstNode::new(Token::Assign, span);
+		new_node.children.push(identifier_node);
+		new_node.children.push(value_node);
+		return Ok((new_node, next_pos));
+	}
+	parse_level_1(tokens, pos)
+}
+
 // level 1 precedence (lowest): to, of
 /// Parse [`To`](crate::TextOperator::To) and [`Of`](crate::TextOperator::Of)
-pub fn parse_level_1(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), String> {
+pub fn parse_level_1(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
 	// do higher precedences first, then come back down
 	let (mut node, mut pos) = parse_level_2(tokens, pos)?;
 	// now we loop through the next tokens
 	loop {
-		let token = tokens.get(pos);
+		let token = token_at(tokens, pos);
 		match token {
 			// if there's a match, we once again do higher precedences, then come
 			// back down again and continue the loop
 			Some(&Token::TextOperator(To)) | Some(&Token::TextOperator(Of)) => {
 				let (right_node, next_pos) = parse_level_2(tokens, pos + 1)?;
-				let mut new_node = AstNode::new(token.unwrap().clone());
+				let span = node.span.to(right_node.span);
+				let mut new_node = AstNode::new(token.unwrap().clone(), span);
 				new_node.children.push(node);
 				new_node.children.push(right_node);
 				node = new_node;
@@ -64,16 +111,65 @@ pub fn parse_level_1(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), S
 	}
 }
 
-// level 2 precedence: +, -
+/// A function that parses an operand starting at `pos`
+type ParseFn = fn(&[SpannedToken], usize) -> Result<(AstNode, usize), Error>;
+
+/// Parse a left-associative chain of any of `operators`, like `1 or 2 or 4`,
+/// where the operands are parsed with `parse_operand`
+fn parse_left_associative(
+	tokens: &[SpannedToken],
+	pos: usize,
+	operators: &[Operator],
+	parse_operand: ParseFn,
+) -> Result<(AstNode, usize), Error> {
+	let (mut node, mut pos) = parse_operand(tokens, pos)?;
+	while let Some(Token::Operator(operator)) = token_at(tokens, pos) {
+		if !operators.contains(operator) {
+			break;
+		}
+		let (right_node, next_pos) = parse_operand(tokens, pos + 1)?;
+		let span = node.span.to(right_node.span);
+		let mut new_node = AstNode::new(Token::Operator(operator.clone()), span);
+		new_node.children.push(node);
+		new_node.children.push(right_node);
+		node = new_node;
+		pos = next_pos;
+	}
+	Ok((node, pos))
+}
+
+// level 2 precedence: or, then xor, and, and <<, >>, like in C
+/// Parse [`BitOr`](crate::Operator::BitOr), [`BitXor`](crate::Operator::BitXor),
+/// [`BitAnd`](crate::Operator::BitAnd), [`ShiftLeft`](crate::Operator::ShiftLeft)
+/// and [`ShiftRight`](crate::Operator::ShiftRight), from lowest to highest
+/// precedence
+pub fn parse_level_2(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
+	parse_left_associative(tokens, pos, &[BitOr], parse_bit_xor)
+}
+
+fn parse_bit_xor(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
+	parse_left_associative(tokens, pos, &[BitXor], parse_bit_and)
+}
+
+fn parse_bit_and(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
+	parse_left_associative(tokens, pos, &[BitAnd], parse_shift)
+}
+
+fn parse_shift(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
+	parse_left_associative(tokens, pos, &[ShiftLeft, ShiftRight], parse_level_3)
+}
+
+// level 3 precedence: +, -
 /// Parse [`Plus`](crate::Operator::Plus) and [`Minus`](crate::Operator::Minus)
-pub fn parse_level_2(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), String> {
-	let (mut node, mut pos) = parse_level_3(tokens, pos)?;
+pub fn parse_level_3(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
+	let (mut node, mut pos) = parse_level_4(tokens, pos)?;
 	loop {
-		let token = tokens.get(pos);
+		let token = token_at(tokens, pos);
 		match token {
 			Some(&Token::Operator(Plus)) | Some(&Token::Operator(Minus)) => {
-				let (right_node, next_pos) = parse_level_3(tokens, pos + 1)?;
-				let mut new_node = AstNode::new(token.unwrap().clone());
+				let (right_node, next_pos) = parse_level_4(tokens, pos + 1)?;
+				let span = node.span.to(right_node.span);
+				let mut new_node = AstNode::new(token.unwrap().clone(), span);
 				new_node.children.push(node);
 				new_node.children.push(right_node);
 				node = new_node;
@@ -86,47 +182,82 @@ pub fn parse_level_2(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), S
 	}
 }
 
-// level 3 precedence: *, /, modulo, implicative multiplication, foot-inch 6'4"
-/// Parse [`Multiply`](crate::Operator::Multiply), [`Divide`](crate::Operator::Divide), [`Modulo`](crate::Operator::Modulo) and implicative multiplication (for example`2pi`)
-pub fn parse_level_3(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), String> {
-	// parse foot-inch syntax 6'4"
-	let token0 = tokens.get(pos);
-	if let Some(Token::Number(_number)) = token0 {
-		let token1 = tokens.get(pos + 1);
-		if let Some(Token::Unit(Foot)) = token1 {
-			let token2 = tokens.get(pos + 2);
-			if let Some(Token::Number(_number)) = token2 {
-				let token3 = tokens.get(pos + 3);
-				if let Some(Token::Unit(Inch)) = token3 {
-					let new_node = AstNode {
-						children: vec![
-							AstNode {
-								children: vec![AstNode::new(token0.unwrap().clone())],
-								token: Token::Unit(Foot),
-							},
-							AstNode {
-								children: vec![AstNode::new(token2.unwrap().clone())],
-								token: Token::Unit(Inch),
-							},
-						],
-						token: Token::Operator(Plus),
-					};
-					return Ok((new_node, pos + 4));
-				}
+/// Parse juxtaposed units, like the foot-inch syntax `6'4"` or `2h 15min 30s`,
+/// into a sum. Each value needs a unit of the same
+/// [`UnitType`](crate::units::UnitType) as the first one, and units have to be
+/// in descending order. A leading minus negates the whole sum, so `-2h 15min`
+/// is -2.25 hours. Returns `None` if there's only one value.
+fn parse_juxtaposed_units(tokens: &[SpannedToken], pos: usize) -> Option<(AstNode, usize)> {
+	if let Some(&Token::Operator(Minus)) = token_at(tokens, pos) {
+		let (node, end) = parse_juxtaposed_units(tokens, pos + 1)?;
+		let span = span_at(tokens, pos).to(node.span);
+		let mut negative_node = AstNode::new(Token::Negative, span);
+		negative_node.children.push(node);
+		return Some((negative_node, end));
+	}
+	let mut node: Option<AstNode> = None;
+	let mut last_unit: Option<Unit> = None;
+	let mut end = pos;
+	while let (Some(Token::Number(_)), Some(&Token::Unit(unit))) =
+		(token_at(tokens, end), token_at(tokens, end + 1))
+	{
+		if !unit.category().allows_mixed_units() {
+			break;
+		}
+		if let Some(last_unit) = last_unit {
+			if unit.category() != last_unit.category() || unit.weight() >= last_unit.weight() {
+				break;
 			}
 		}
+		let span = span_at(tokens, end).to(span_at(tokens, end + 1));
+		let value_node = AstNode {
+			children: vec![AstNode::new(
+				token_at(tokens, end)?.clone(),
+				span_at(tokens, end),
+			)],
+			token: Token::Unit(unit),
+			span,
+		};
+		node = Some(match node {
+			None => value_node,
+			Some(left_node) => AstNode {
+				span: left_node.span.to(span),
+				children: vec![left_node, value_node],
+				token: Token::Operator(Plus),
+			},
+		});
+		last_unit = Some(unit);
+		end += 2;
+	}
+	match node {
+		Some(node) if end - pos > 2 => Some((node, end)),
+		_ => None,
 	}
+}
 
-	let (mut node, mut pos) = parse_level_4(tokens, pos)?;
+/// Parse an operand of level 4, which is either juxtaposed units like
+/// `1h 30min`, or anything of a higher precedence
+fn parse_operand_4(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
+	match parse_juxtaposed_units(tokens, pos) {
+		Some(juxtaposed) => Ok(juxtaposed),
+		None => parse_level_5(tokens, pos),
+	}
+}
+
+// level 4 precedence: *, /, modulo, implicative multiplication, juxtaposed units 6'4"
+/// Parse [`Multiply`](crate::Operator::Multiply), [`Divide`](crate::Operator::Divide), [`Modulo`](crate::Operator::Modulo), implicative multiplication (for example`2pi`) and juxtaposed units (for example `2h 15min`)
+pub fn parse_level_4(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
+	let (mut node, mut pos) = parse_operand_4(tokens, pos)?;
 
 	loop {
-		let token = tokens.get(pos);
+		let token = token_at(tokens, pos);
 		match token {
 			Some(&Token::Operator(Multiply))
 			| Some(&Token::Operator(Divide))
 			| Some(&Token::Operator(Modulo)) => {
-				let (right_node, next_pos) = parse_level_4(tokens, pos + 1)?;
-				let mut new_node = AstNode::new(token.unwrap().clone());
+				let (right_node, next_pos) = parse_operand_4(tokens, pos + 1)?;
+				let span = node.span.to(right_node.span);
+				let mut new_node = AstNode::new(token.unwrap().clone(), span);
 				new_node.children.push(node);
 				new_node.children.push(right_node);
 				node = new_node;
@@ -142,11 +273,12 @@ pub fn parse_level_3(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), S
 
 			// pi2, )2
 			Some(&Token::Number(_)) => {
-				let last_token = tokens.get(pos - 1);
+				let last_token = token_at(tokens, pos - 1);
 				match last_token {
 					Some(&Token::Constant(_)) | Some(&Token::Operator(RightParen)) => {
-						let (right_node, next_pos) = parse_level_4(tokens, pos)?;
-						let mut new_node = AstNode::new(Token::Operator(Multiply));
+						let (right_node, next_pos) = parse_level_5(tokens, pos)?;
+						let span = node.span.to(right_node.span);
+						let mut new_node = AstNode::new(Token::Operator(Multiply), span);
 						new_node.children.push(node);
 						new_node.children.push(right_node);
 						node = new_node;
@@ -157,13 +289,14 @@ pub fn parse_level_3(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), S
 					}
 				}
 			}
-			// 2pi, )pi
-			Some(&Token::Constant(_)) => {
-				let last_token = tokens.get(pos - 1);
+			// 2pi, )pi, 2ans, 2x
+			Some(&Token::Constant(_)) | Some(&Token::Ans) | Some(&Token::Identifier(_)) => {
+				let last_token = token_at(tokens, pos - 1);
 				match last_token {
 					Some(&Token::Number(_)) | Some(&Token::Operator(RightParen)) => {
-						let (right_node, next_pos) = parse_level_4(tokens, pos)?;
-						let mut new_node = AstNode::new(Token::Operator(Multiply));
+						let (right_node, next_pos) = parse_level_5(tokens, pos)?;
+						let span = node.span.to(right_node.span);
+						let mut new_node = AstNode::new(Token::Operator(Multiply), span);
 						new_node.children.push(node);
 						new_node.children.push(right_node);
 						node = new_node;
@@ -176,11 +309,12 @@ pub fn parse_level_3(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), S
 			}
 			// 2log(1), )log(1)
 			Some(&Token::FunctionIdentifier(_)) => {
-				let last_token = tokens.get(pos - 1);
+				let last_token = token_at(tokens, pos - 1);
 				match last_token {
 					Some(&Token::Number(_)) | Some(&Token::Operator(RightParen)) => {
-						let (right_node, next_pos) = parse_level_4(tokens, pos)?;
-						let mut new_node = AstNode::new(Token::Operator(Multiply));
+						let (right_node, next_pos) = parse_level_5(tokens, pos)?;
+						let span = node.span.to(right_node.span);
+						let mut new_node = AstNode::new(Token::Operator(Multiply), span);
 						new_node.children.push(node);
 						new_node.children.push(right_node);
 						node = new_node;
@@ -193,13 +327,14 @@ pub fn parse_level_3(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), S
 			}
 			// 2(3), pi(3), )(3)
 			Some(&Token::Operator(LeftParen)) => {
-				let last_token = tokens.get(pos - 1);
+				let last_token = token_at(tokens, pos - 1);
 				match last_token {
 					Some(&Token::Number(_))
 					| Some(&Token::Constant(_))
 					| Some(&Token::Operator(RightParen)) => {
-						let (right_node, next_pos) = parse_level_4(tokens, pos)?;
-						let mut new_node = AstNode::new(Token::Operator(Multiply));
+						let (right_node, next_pos) = parse_level_5(tokens, pos)?;
+						let span = node.span.to(right_node.span);
+						let mut new_node = AstNode::new(Token::Operator(Multiply), span);
 						new_node.children.push(node);
 						new_node.children.push(right_node);
 						node = new_node;
@@ -217,16 +352,17 @@ pub fn parse_level_3(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), S
 	}
 }
 
-// level 4 precedence: ^
+// level 5 precedence: ^
 /// Parse [`Caret`](crate::Operator::Caret)
-pub fn parse_level_4(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), String> {
-	let (mut node, mut pos) = parse_level_5(tokens, pos)?;
+pub fn parse_level_5(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
+	let (mut node, mut pos) = parse_level_6(tokens, pos)?;
 	loop {
-		let token = tokens.get(pos);
+		let token = token_at(tokens, pos);
 		match token {
 			Some(&Token::Operator(Caret)) => {
-				let (right_node, next_pos) = parse_level_5(tokens, pos + 1)?;
-				let mut new_node = AstNode::new(token.unwrap().clone());
+				let (right_node, next_pos) = parse_level_6(tokens, pos + 1)?;
+				let span = node.span.to(right_node.span);
+				let mut new_node = AstNode::new(token.unwrap().clone(), span);
 				new_node.children.push(node);
 				new_node.children.push(right_node);
 				node = new_node;
@@ -239,37 +375,46 @@ pub fn parse_level_4(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), S
 	}
 }
 
-// level 5 precedence: - (as in -5, but not 4-5)
-/// Parse [`Negative`](Token::Negative)
-pub fn parse_level_5(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), String> {
+// level 6 precedence: - (as in -5, but not 4-5), not
+/// Parse [`Negative`](Token::Negative) and [`BitNot`](crate::Operator::BitNot)
+pub fn parse_level_6(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
 	// Here we parse the negative unary operator. If the current token
 	// is a minus, we wrap the right_node inside a Negative AstNode.
 	//
 	// Why doesn't this parse 4-5? First, we will first get a 4. In which case,
-	// we just return the result of parse_level_6(), which will include the pos
-	// of +. This will then go down to level 2 and be parsed as a normal minus
+	// we just return the result of parse_level_7(), which will include the pos
+	// of +. This will then go down to level 3 and be parsed as a normal minus
 	// operator.
 	// The difference is that in other levels, we parse higher priorities
 	// immediately, while in this one we instead check if the current token
 	// is a minus, and if not, we then return the higher priority as normal.
-	let token = tokens.get(pos);
+	let token = token_at(tokens, pos);
 	match token {
 		Some(&Token::Operator(Minus)) => {
+			let (right_node, next_pos) = parse_level_7(tokens, pos + 1)?;
+			let span = span_at(tokens, pos).to(right_node.span);
+			let mut new_node = AstNode::new(Token::Negative, span);
+			new_node.children.push(right_node);
+			Ok((new_node, next_pos))
+		}
+		// not can be repeated, like "not not 5" or "not -5"
+		Some(&Token::Operator(BitNot)) => {
 			let (right_node, next_pos) = parse_level_6(tokens, pos + 1)?;
-			let mut new_node = AstNode::new(Token::Negative);
+			let span = span_at(tokens, pos).to(right_node.span);
+			let mut new_node = AstNode::new(Token::Operator(BitNot), span);
 			new_node.children.push(right_node);
 			Ok((new_node, next_pos))
 		}
-		_ => parse_level_6(tokens, pos),
+		_ => parse_level_7(tokens, pos),
 	}
 }
 
-// level 6 precedence: !, percent, units attached to values
+// level 7 precedence: !, percent, units attached to values
 /// Parse [`Factorial`](crate::UnaryOperator::Factorial) and [`Percent`](crate::UnaryOperator::Percent)
-pub fn parse_level_6(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), String> {
-	let (mut node, mut pos) = parse_level_7(tokens, pos)?;
+pub fn parse_level_7(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
+	let (mut node, mut pos) = parse_level_8(tokens, pos)?;
 	loop {
-		let token = tokens.get(pos);
+		let token = token_at(tokens, pos);
 		match token {
 			Some(&Token::UnaryOperator(Factorial))
 			| Some(&Token::UnaryOperator(Percent))
@@ -277,15 +422,41 @@ pub fn parse_level_6(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), S
 				// Here we are handling unary operators, aka stuff written as
 				// "Number Operator" (3!) instead of "Number Operator Number" (3+3).
 				// Therefore, if we find a match, we don't parse what comes after it.
-				let mut new_node = AstNode::new(token.unwrap().clone());
+				let span = node.span.to(span_at(tokens, pos));
+				let mut new_node = AstNode::new(token.unwrap().clone(), span);
 				new_node.children.push(node);
 				node = new_node;
 				pos += 1;
 			}
 			Some(&Token::Unit(_unit)) => {
+				// A power directly after a unit belongs to the unit, so "2 m^2" is
+				// 2 times m^2 instead of (2 m)^2, the same as the s in "kg*m/s^2"
+				if let Some(&Token::Operator(Caret)) = token_at(tokens, pos + 1) {
+					let unit_node = AstNode::new(token.unwrap().clone(), span_at(tokens, pos));
+					let (exponent_node, next_pos) = parse_level_6(tokens, pos + 2)?;
+					let mut caret_node = AstNode::new(
+						Token::Operator(Caret),
+						unit_node.span.to(exponent_node.span),
+					);
+					caret_node.children.push(unit_node);
+					caret_node.children.push(exponent_node);
+					let span = node.span.to(caret_node.span);
+					let mut new_node = AstNode::new(Token::Operator(Multiply), span);
+					new_node.children.push(node);
+					new_node.children.push(caret_node);
+					return Ok((new_node, next_pos));
+				}
 				// We won't allow units to repeat, like "1min min", so we end the loop if it's found.
-				let mut new_node = AstNode::new(token.unwrap().clone());
+				let span = node.span.to(span_at(tokens, pos));
+				let mut new_node = AstNode::new(token.unwrap().clone(), span);
 				new_node.children.push(node);
+				// except for a time zone after a time or date, like "3pm PST"
+				if let Some(Token::Unit(zone @ Unit::Zoned(_))) = token_at(tokens, pos + 1) {
+					let span = new_node.span.to(span_at(tokens, pos + 1));
+					let mut zone_node = AstNode::new(Token::Unit(*zone), span);
+					zone_node.children.push(new_node);
+					return Ok((zone_node, pos + 2));
+				}
 				return Ok((new_node, pos + 1));
 			}
 			_ => {
@@ -298,75 +469,69 @@ pub fn parse_level_6(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), S
 	}
 }
 
-// level 7 precedence: numbers, standalone units, constants, functions, parens
+// level 8 precedence: numbers, standalone units, constants, variables, functions, parens
 /// Parse [`Number`](Token::Number),
 /// [`Unit`](Token::Unit),
 /// [`Constant`](Token::Constant),
+/// [`Ans`](Token::Ans),
+/// [`Identifier`](Token::Identifier),
 /// [`FunctionIdentifier`](Token::FunctionIdentifier),
 /// [`Paren`](Token::Paren)
-pub fn parse_level_7(tokens: &[Token], pos: usize) -> Result<(AstNode, usize), String> {
-	let token: &Token = tokens
-		.get(pos)
-		.ok_or(format!("Unexpected end of input at {}", pos))?;
+pub fn parse_level_8(tokens: &[SpannedToken], pos: usize) -> Result<(AstNode, usize), Error> {
+	let token: &Token =
+		token_at(tokens, pos).ok_or_else(|| unexpected_token(tokens, pos, "paren or number"))?;
+	let span = span_at(tokens, pos);
 	match token {
 		&Token::Number(_number) => {
-			let node = AstNode::new(token.clone());
+			let node = AstNode::new(token.clone(), span);
 			Ok((node, pos + 1))
 		}
 		&Token::Unit(_unit) => {
-			let node = AstNode::new(token.clone());
+			let node = AstNode::new(token.clone(), span);
 			Ok((node, pos + 1))
 		}
-		Token::Constant(_constant) => {
-			let node = AstNode::new(token.clone());
+		Token::Constant(_) | Token::Ans | Token::Identifier(_) | Token::NumberFormat(_) => {
+			let node = AstNode::new(token.clone(), span);
 			Ok((node, pos + 1))
 		}
 		Token::FunctionIdentifier(_function_identifier) => {
 			let left_paren_pos = pos + 1;
-			let left_paren_token = tokens.get(left_paren_pos);
+			let left_paren_token = token_at(tokens, left_paren_pos);
 			// check if '(' comes after function identifier, like 'log('
 			match left_paren_token {
 				Some(&Token::Operator(LeftParen)) => {
-					// parse everything inside as you would with normal parentheses,
-					// then put it inside an ast node.
-					parse_level_1(tokens, left_paren_pos + 1).and_then(|(node, next_pos)| {
-						if let Some(&Token::Operator(RightParen)) = tokens.get(next_pos) {
-							let mut function_node = AstNode::new(token.clone());
-							function_node.children.push(node);
-							Ok((function_node, next_pos + 1))
-						} else {
-							Err(format!(
-								"Expected closing paren at {} but found {:?}",
-								next_pos,
-								tokens.get(next_pos)
-							))
+					// parse each comma-separated argument as you would with normal
+					// parentheses, then put them inside an ast node.
+					let mut function_node = AstNode::new(token.clone(), span);
+					let mut next_pos = left_paren_pos;
+					loop {
+						let (node, arg_end_pos) = parse_level_1(tokens, next_pos + 1)?;
+						function_node.children.push(node);
+						next_pos = arg_end_pos;
+						match token_at(tokens, next_pos) {
+							Some(&Token::Operator(Comma)) => {}
+							Some(&Token::Operator(RightParen)) => break,
+							_ => return Err(unexpected_token(tokens, next_pos, "closing paren")),
 						}
-					})
+					}
+					function_node.span = span.to(span_at(tokens, next_pos));
+					Ok((function_node, next_pos + 1))
 				}
-				_ => Err(format!(
-					"Expected ( after {} at {:?} but found {:?}",
-					left_paren_pos, token, left_paren_token
-				)),
+				_ => Err(unexpected_token(tokens, left_paren_pos, "( after function")),
 			}
 		}
 		Token::Operator(LeftParen) => {
 			parse_level_1(tokens, pos + 1).and_then(|(node, next_pos)| {
-				if let Some(&Token::Operator(RightParen)) = tokens.get(next_pos) {
-					let mut paren_node = AstNode::new(Token::Paren);
+				if let Some(&Token::Operator(RightParen)) = token_at(tokens, next_pos) {
+					let span = span.to(span_at(tokens, next_pos));
+					let mut paren_node = AstNode::new(Token::Paren, span);
 					paren_node.children.push(node);
 					Ok((paren_node, next_pos + 1))
 				} else {
-					Err(format!(
-						"Expected closing paren at {} but found {:?}",
-						next_pos,
-						tokens.get(next_pos)
-					))
+					Err(unexpected_token(tokens, next_pos, "closing paren"))
 				}
 			})
 		}
-		_ => Err(format!(
-			"Unexpected token {:?}, expected paren or number",
-			token
-		)),
+		_ => Err(unexpected_token(tokens, pos, "paren or number")),
 	}
 }
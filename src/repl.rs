@@ -0,0 +1,183 @@
+use crate::{print_answer, print_error};
+use cpc::currency::exchange_rates;
+use cpc::units::{Unit, UnitType};
+use cpc::{eval_with_context, Context};
+use rustyline::error::ReadlineError;
+use rustyline::Editor;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+fn print_repl_help() {
+	println!(concat!(
+		"Enter an expression to evaluate it. The previous answer is available as `ans` or `_`.",
+		"\n",
+		"\nCommands:",
+		"\n    :verbose    Toggle verbose logging",
+		"\n    :units      List supported units",
+		"\n    :help       Show this help page",
+		"\n    :quit       Exit (or press Ctrl-D)",
+	));
+}
+
+/// Print every [`Unit`], grouped by [`UnitType`]
+fn print_units() {
+	let mut units_by_type: Vec<(UnitType, Vec<Unit>)> = Vec::new();
+	for unit in Unit::ALL.iter().filter(|unit| **unit != Unit::NoUnit) {
+		match units_by_type
+			.iter_mut()
+			.find(|(unit_type, _)| *unit_type == unit.category())
+		{
+			Some((_, units)) => units.push(*unit),
+			None => units_by_type.push((unit.category(), vec![*unit])),
+		}
+	}
+	for (unit_type, units) in units_by_type {
+		let names: Vec<String> = units.iter().map(|unit| format!("{:?}", unit)).collect();
+		println!("{:?}: {}", unit_type, names.join(", "));
+	}
+	if let Some(rates) = exchange_rates() {
+		let mut codes: Vec<String> = rates.rates.keys().map(|c| c.to_string()).collect();
+		codes.push(rates.base.to_string());
+		codes.sort();
+		codes.dedup();
+		println!("Currency: {}", codes.join(", "));
+	}
+}
+
+/// The history file, like `~/.local/share/cpc/history` on Linux
+fn history_path() -> Option<PathBuf> {
+	dirs::data_dir().map(|dir| dir.join("cpc").join("history"))
+}
+
+/// Whether the REPL should keep reading lines after a line is handled
+#[derive(Debug, PartialEq)]
+enum Flow {
+	Continue,
+	Quit,
+}
+
+/// Run a command or evaluate an expression. Answers are stored in `context`,
+/// so the next line can use them as `ans` or `_`.
+fn handle_line(input: &str, context: &mut Context, verbose: &mut bool) -> Flow {
+	match input {
+		":q" | ":quit" | ":exit" => return Flow::Quit,
+		":h" | ":help" => print_repl_help(),
+		":units" => print_units(),
+		":verbose" => {
+			*verbose = !*verbose;
+			println!("Verbose logging {}", if *verbose { "on" } else { "off" });
+		}
+		command if command.starts_with(':') => {
+			eprintln!("Unknown command: {}. Type :help for help", command);
+		}
+		_ => match eval_with_context(input, context, true, Unit::Celsius, *verbose) {
+			Ok(answer) => {
+				if !*verbose {
+					print_answer(&answer);
+				}
+			}
+			Err(e) => print_error(input, &e),
+		},
+	}
+	Flow::Continue
+}
+
+/// Save the history to `path`, creating its directory if needed
+fn save_history(editor: &mut Editor<()>, path: &Path) -> rustyline::Result<()> {
+	if let Some(dir) = path.parent() {
+		let _ = fs::create_dir_all(dir);
+	}
+	editor.save_history(path)
+}
+
+/// Start an interactive session, which runs until the user quits
+pub fn run(mut verbose: bool) {
+	let mut editor = match Editor::<()>::new() {
+		Ok(editor) => editor,
+		Err(e) => {
+			eprintln!("Unable to start REPL: {}", e);
+			exit(1);
+		}
+	};
+	let history_path = history_path();
+	if let Some(path) = &history_path {
+		// the history file doesn't exist until the first session ends
+		let _ = editor.load_history(path);
+	}
+
+	let mut context = Context::default();
+	loop {
+		let line = match editor.readline("> ") {
+			Ok(line) => line,
+			Err(ReadlineError::Interrupted) => continue,
+			Err(ReadlineError::Eof) => break,
+			Err(e) => {
+				eprintln!("{}", e);
+				break;
+			}
+		};
+		let input = line.trim();
+		if input.is_empty() {
+			continue;
+		}
+		editor.add_history_entry(input);
+		if handle_line(input, &mut context, &mut verbose) == Flow::Quit {
+			break;
+		}
+	}
+
+	if let Some(path) = &history_path {
+		if let Err(e) = save_history(&mut editor, path) {
+			eprintln!("Unable to save history: {}", e);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_repl() {
+		let ans = |context: &Context| context.ans.as_ref().unwrap().to_string();
+		let mut context = Context::default();
+		let mut verbose = false;
+		assert_eq!(
+			handle_line("5 km", &mut context, &mut verbose),
+			Flow::Continue
+		);
+		assert_eq!(ans(&context), "5 Kilometer");
+		handle_line("ans * 2", &mut context, &mut verbose);
+		assert_eq!(ans(&context), "10 Kilometer");
+		handle_line("_ + 1 km", &mut context, &mut verbose);
+		assert_eq!(ans(&context), "11 Kilometer");
+		// errors keep the previous answer
+		handle_line("ans + 1 kg", &mut context, &mut verbose);
+		assert_eq!(ans(&context), "11 Kilometer");
+
+		assert_eq!(
+			handle_line(":verbose", &mut context, &mut verbose),
+			Flow::Continue
+		);
+		assert!(verbose);
+		assert_eq!(
+			handle_line(":foo", &mut context, &mut verbose),
+			Flow::Continue
+		);
+		assert_eq!(handle_line(":quit", &mut context, &mut verbose), Flow::Quit);
+		assert_eq!(handle_line(":q", &mut context, &mut verbose), Flow::Quit);
+
+		let dir = std::env::temp_dir().join(format!("cpc-test-{}", std::process::id()));
+		let path = dir.join("cpc").join("history");
+		let mut editor = Editor::<()>::new().unwrap();
+		editor.add_history_entry("5 km");
+		editor.add_history_entry("ans * 2");
+		save_history(&mut editor, &path).unwrap();
+		let mut editor = Editor::<()>::new().unwrap();
+		editor.load_history(&path).unwrap();
+		let history: Vec<&String> = editor.history().iter().collect();
+		assert_eq!(history, ["5 km", "ans * 2"]);
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
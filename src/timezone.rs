@@ -0,0 +1,255 @@
+use crate::Error;
+use chrono::{FixedOffset, LocalResult, NaiveDateTime, Offset, TimeZone as _};
+use chrono_tz::{OffsetName, Tz, TZ_VARIANTS};
+use std::fmt::{self, Debug, Display};
+
+/// Time zone abbreviations in uppercase, with their offset from UTC in seconds and a zone
+/// that uses them. Abbreviations that are listed more than once are
+/// ambiguous.
+const ABBREVIATIONS: &[(&str, i32, &str)] = &[
+	("UTC", 0, "Etc/UTC"),
+	("GMT", 0, "Europe/London"),
+	("WET", 0, "Europe/Lisbon"),
+	("WEST", 3600, "Europe/Lisbon"),
+	("BST", 3600, "Europe/London"),
+	("BST", 21600, "Asia/Dhaka"),
+	("IST", 3600, "Europe/Dublin"),
+	("IST", 7200, "Asia/Jerusalem"),
+	("IST", 19800, "Asia/Kolkata"),
+	("CET", 3600, "Europe/Paris"),
+	("CEST", 7200, "Europe/Paris"),
+	("EET", 7200, "Europe/Athens"),
+	("EEST", 10800, "Europe/Athens"),
+	("MSK", 10800, "Europe/Moscow"),
+	("WAT", 3600, "Africa/Lagos"),
+	("CAT", 7200, "Africa/Maputo"),
+	("SAST", 7200, "Africa/Johannesburg"),
+	("EAT", 10800, "Africa/Nairobi"),
+	("GST", -7200, "Atlantic/South_Georgia"),
+	("GST", 14400, "Asia/Dubai"),
+	("PKT", 18000, "Asia/Karachi"),
+	("ICT", 25200, "Asia/Bangkok"),
+	("WIB", 25200, "Asia/Jakarta"),
+	("CST", -21600, "America/Chicago"),
+	("CST", -18000, "America/Havana"),
+	("CST", 28800, "Asia/Shanghai"),
+	("HKT", 28800, "Asia/Hong_Kong"),
+	("SGT", 28800, "Asia/Singapore"),
+	("AWST", 28800, "Australia/Perth"),
+	("JST", 32400, "Asia/Tokyo"),
+	("KST", 32400, "Asia/Seoul"),
+	("ACST", 34200, "Australia/Adelaide"),
+	("ACDT", 37800, "Australia/Adelaide"),
+	("AEST", 36000, "Australia/Sydney"),
+	("AEDT", 39600, "Australia/Sydney"),
+	("NZST", 43200, "Pacific/Auckland"),
+	("NZDT", 46800, "Pacific/Auckland"),
+	("NDT", -9000, "America/St_Johns"),
+	("NST", -12600, "America/St_Johns"),
+	("BRT", -10800, "America/Sao_Paulo"),
+	("ART", -10800, "America/Argentina/Buenos_Aires"),
+	("ADT", -10800, "America/Halifax"),
+	("AST", -14400, "America/Halifax"),
+	("AST", 10800, "Asia/Riyadh"),
+	("EDT", -14400, "America/New_York"),
+	("EST", -18000, "America/New_York"),
+	("CDT", -18000, "America/Chicago"),
+	("CDT", -14400, "America/Havana"),
+	("MDT", -21600, "America/Denver"),
+	("MST", -25200, "America/Denver"),
+	("PDT", -25200, "America/Los_Angeles"),
+	("PST", -28800, "America/Los_Angeles"),
+	("AKDT", -28800, "America/Anchorage"),
+	("AKST", -32400, "America/Anchorage"),
+	("HST", -36000, "Pacific/Honolulu"),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+/// A time zone, used to show dates in local time
+pub enum TimeZone {
+	/// A zone from the tz database, like `Europe/Berlin`. The offset depends
+	/// on the date, so daylight saving time is taken into account.
+	Named(Tz),
+	/// A fixed offset from UTC in seconds, written as an abbreviation like
+	/// `PST`. The name is in uppercase.
+	Abbreviation { name: &'static str, offset: i32 },
+}
+
+impl TimeZone {
+	/// Finds a time zone by its name in the tz database, like
+	/// `europe/berlin`, or by the last part of its name, like `tokyo` or
+	/// `new_york`. Names are case insensitive.
+	pub fn from_name(name: &str) -> Option<TimeZone> {
+		let full_name = TZ_VARIANTS
+			.iter()
+			.find(|tz| tz.name().eq_ignore_ascii_case(name));
+		let city = || {
+			TZ_VARIANTS
+				.iter()
+				.find(|tz| match tz.name().rsplit_once('/') {
+					Some((_, city)) => city.eq_ignore_ascii_case(name),
+					None => false,
+				})
+		};
+		full_name.or_else(city).map(|tz| TimeZone::Named(*tz))
+	}
+
+	/// Finds a time zone by its abbreviation, like `pst`. Returns
+	/// [`Error::AmbiguousTimeZone`] if the abbreviation is used for
+	/// different offsets, like `cst`, and `Ok(None)` if it's unknown.
+	pub fn from_abbreviation(abbreviation: &str) -> Result<Option<TimeZone>, Error> {
+		let matches: Vec<_> = ABBREVIATIONS
+			.iter()
+			.filter(|(name, _, _)| name.eq_ignore_ascii_case(abbreviation))
+			.collect();
+		match matches.as_slice() {
+			[] => Ok(None),
+			[(name, offset, _)] => Ok(Some(TimeZone::Abbreviation {
+				name,
+				offset: *offset,
+			})),
+			_ => Err(Error::AmbiguousTimeZone {
+				abbreviation: abbreviation.to_uppercase(),
+				candidates: matches
+					.iter()
+					.map(|(_, offset, zone)| format!("{} ({})", zone, format_offset(*offset)))
+					.collect(),
+				span: None,
+			}),
+		}
+	}
+
+	/// The offset from UTC at a time in UTC
+	pub fn offset(&self, utc: NaiveDateTime) -> FixedOffset {
+		match self {
+			TimeZone::Named(tz) => tz.offset_from_utc_datetime(&utc).fix(),
+			TimeZone::Abbreviation { offset, .. } => fixed_offset(*offset),
+		}
+	}
+
+	/// Converts a local date and time in this time zone to UTC. Returns
+	/// `None` if the local time doesn't exist, because clocks were set
+	/// forward. If it exists twice, because clocks were set back, the
+	/// earlier one is used.
+	pub fn to_utc(&self, local: NaiveDateTime) -> Option<NaiveDateTime> {
+		let offset = match self {
+			TimeZone::Named(tz) => tz
+				.offset_from_local_datetime(&local)
+				.map(|offset| offset.fix()),
+			TimeZone::Abbreviation { offset, .. } => LocalResult::Single(fixed_offset(*offset)),
+		};
+		Some(local - offset.earliest()?)
+	}
+
+	/// The abbreviation of this time zone at a time in UTC, like `CEST`
+	/// for `Europe/Berlin` in the summer
+	pub fn abbreviation(&self, utc: NaiveDateTime) -> String {
+		match self {
+			TimeZone::Named(tz) => {
+				let offset = tz.offset_from_utc_datetime(&utc);
+				match offset.abbreviation() {
+					Some(abbreviation) => abbreviation.to_string(),
+					None => format_offset(offset.fix().local_minus_utc()),
+				}
+			}
+			TimeZone::Abbreviation { name, .. } => name.to_string(),
+		}
+	}
+}
+
+fn fixed_offset(seconds: i32) -> FixedOffset {
+	FixedOffset::east_opt(seconds).unwrap()
+}
+
+/// Formats an offset in seconds like `UTC+5:30`
+fn format_offset(seconds: i32) -> String {
+	let sign = if seconds < 0 { '-' } else { '+' };
+	let (hours, minutes) = (seconds.abs() / 3600, seconds.abs() % 3600 / 60);
+	match minutes {
+		0 => format!("UTC{}{}", sign, hours),
+		_ => format!("UTC{}{}:{:02}", sign, hours, minutes),
+	}
+}
+
+impl Display for TimeZone {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			TimeZone::Named(tz) => write!(f, "{}", tz.name()),
+			TimeZone::Abbreviation { name, .. } => write!(f, "{}", name),
+		}
+	}
+}
+
+impl Debug for TimeZone {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		Display::fmt(self, f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::units::Unit;
+	use crate::{eval_with_context, Context};
+	use std::time::{Duration, UNIX_EPOCH};
+
+	#[test]
+	fn test_time_zones() {
+		// 2026-10-18 15:30:00 UTC
+		let mut context = Context {
+			now: Some(UNIX_EPOCH + Duration::from_secs(1792337400)),
+			..Context::default()
+		};
+		let mut eval = |input| eval_with_context(input, &mut context, true, Unit::Celsius, false);
+		let mut eval_ok = |input| eval(input).unwrap().to_string();
+		assert_eq!(eval_ok("3pm PST to CET"), "2026-10-19 00:00:00 CET");
+		assert_eq!(
+			eval_ok("14:00 Europe/Berlin to America/New_York"),
+			"2026-10-18 08:00:00 EDT"
+		);
+		assert_eq!(
+			eval_ok("2026-12-01T14:00 europe/berlin to america/new_york"),
+			"2026-12-01 08:00:00 EST"
+		);
+		// Europe changes back a week before the US
+		assert_eq!(
+			eval_ok("2026-10-28T14:00 europe/berlin to america/new_york"),
+			"2026-10-28 09:00:00 EDT"
+		);
+		assert_eq!(
+			eval_ok("2026-10-25T02:30 europe/berlin to utc"),
+			"2026-10-25 00:30:00 UTC"
+		);
+		assert_eq!(eval_ok("now in Tokyo"), "2026-10-19 00:30:00 JST");
+		assert_eq!(eval_ok("now in kolkata"), "2026-10-18 21:00:00 IST");
+		assert_eq!(
+			eval_ok("2026-01-31T09:00 europe/berlin + 1 month to utc"),
+			"2026-02-28 08:00:00 UTC"
+		);
+		assert_eq!(
+			eval_ok("9:30am america/argentina/buenos_aires"),
+			"2026-10-18 09:30:00 UTC-3"
+		);
+
+		assert!(matches!(
+			eval("3pm cst"),
+			Err(Error::AmbiguousTimeZone { .. })
+		));
+		assert!(matches!(
+			eval("2026-03-29T02:30 europe/berlin"),
+			Err(Error::InvalidTime { .. })
+		));
+		assert!(matches!(eval("13:60 utc"), Err(Error::InvalidTime { .. })));
+		assert_eq!(
+			TimeZone::from_abbreviation("pst").unwrap(),
+			Some(TimeZone::Abbreviation {
+				name: "PST",
+				offset: -28800
+			})
+		);
+		assert_eq!(
+			TimeZone::from_name("new_york"),
+			Some(TimeZone::Named(Tz::America__New_York))
+		);
+	}
+}
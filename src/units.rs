@@ -1,5 +1,8 @@
+use std::fmt::{self, Debug, Display};
+use std::str::FromStr;
 use decimal::d128;
-use crate::Number;
+use crate::{currency, datetime, evaluator, floor, timezone, to_integer, Error, FunctionIdentifier, Number, Operator};
+use crate::dimensions::{format_power, Dimensions};
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 /// An enum of all possible unit types, like [`Length`], [`DigitalStorage`] etc.
@@ -31,14 +34,50 @@ pub enum UnitType {
 	Resistance,
 	/// A unit of voltage, for example [`Volt`]
 	Voltage,
+	/// A unit of electric charge, for example [`Coulomb`] or [`MilliampereHour`]
+	ElectricCharge,
+	/// A unit of capacitance, for example [`Microfarad`]
+	Capacitance,
+	/// A unit of inductance, for example [`Henry`]
+	Inductance,
+	/// A unit of electrical conductance, for example [`Siemens`]
+	Conductance,
+	/// A unit of magnetic flux, for example [`Weber`]
+	MagneticFlux,
+	/// A unit of magnetic flux density, for example [`Tesla`]
+	MagneticFluxDensity,
+	/// A unit of force, for example [`Newton`]
+	Force,
 	/// A unit of pressure, for example [`Bar`]
 	Pressure,
 	/// A unit of frequency, for example [`Hertz`]
 	Frequency,
 	/// A unit of x, for example [`KilometersPerHour`]
 	Speed,
+	/// A unit of acceleration, for example [`MetersPerSecondSquared`]
+	Acceleration,
+	/// A unit of volumetric flow rate, for example [`LitersPerMinute`]
+	VolumetricFlowRate,
+	/// A unit of mass flow rate, for example [`KilogramsPerHour`]
+	MassFlowRate,
+	/// A unit of density, for example [`GramsPerCubicCentimeter`]
+	Density,
+	/// A unit of fuel economy, for example [`MilesPerGallon`] or
+	/// [`LitersPer100Kilometers`]
+	FuelEconomy,
 	/// A unit of temperature, for example [`Kelvin`]
 	Temperature,
+	/// A unit of angle, for example [`Degree`]
+	Angle,
+	/// A point in time, for example a [`Date`]. Values are in seconds since
+	/// the Unix epoch, in UTC.
+	Timestamp,
+	/// A currency, for example `USD`. The weights of currencies come from
+	/// the exchange rates set with [`set_exchange_rates`](crate::set_exchange_rates).
+	Currency,
+	/// A combination of other units, like `kg·m/s²` or `g/cm³`. See
+	/// [`Composite`].
+	Derived,
 }
 use UnitType::*;
 
@@ -50,23 +89,38 @@ macro_rules! create_units {
 		#[derive(Clone, Copy, PartialEq, Debug)]
 		/// A Unit enum. Note that it can also be [`NoUnit`].
 		pub enum Unit {
-			$($variant),*
+			$($variant),*,
+			/// A unit without a name, made up of other units, like `g/cm³`
+			Composite(CompositeUnit),
+			/// A currency, like `USD`
+			Currency(currency::Currency),
+			/// A date and time shown in a time zone, like `Europe/Berlin`
+			Zoned(timezone::TimeZone),
 		}
 		use Unit::*;
 
 		impl Unit {
+			/// Every [`Unit`], in the order they are defined
+			pub const ALL: &'static [Unit] = &[$(Unit::$variant),*];
 			pub fn category(&self) -> UnitType {
 				match self {
 					$(
 						Unit::$variant => $properties.0
-					),*
+					),*,
+					Unit::Composite(_) => Derived,
+					Unit::Currency(_) => UnitType::Currency,
+					Unit::Zoned(_) => Timestamp,
 				}
 			}
 			pub fn weight(&self) -> d128 {
 				match self {
 					$(
 						Unit::$variant => $properties.1
-					),*
+					),*,
+					Unit::Composite(composite) => composite.si_factor(),
+					// NaN if there's no exchange rate, which convert() reports
+					Unit::Currency(currency) => currency.weight().unwrap_or(d128!(NaN)),
+					Unit::Zoned(_) => d128!(1),
 				}
 			}
 		}
@@ -105,6 +159,20 @@ create_units!(
 	NauticalMile:       (Length, d128!(1852000)),
 	LightYear:          (Length, d128!(9460730472580800000)),
 	LightSecond:        (Length, d128!(299792458000)),
+	LightMinute:        (Length, d128!(17987547480000)),
+	AstronomicalUnit:   (Length, d128!(149597870700000)),
+	Parsec:             (Length, d128!(30856775814913672789.13937957796471610732)),
+	EarthRadius:        (Length, d128!(6371008800)),
+	Picometer:          (Length, d128!(0.000000001)),
+	Angstrom:           (Length, d128!(0.0000001)),
+	Nanometer:          (Length, d128!(0.000001)),
+	Micrometer:         (Length, d128!(0.001)),
+	Thou:               (Length, d128!(0.0254)),
+	Fathom:             (Length, d128!(1828.8)),
+	Rod:                (Length, d128!(5029.2)),
+	Chain:              (Length, d128!(20116.8)),
+	Furlong:            (Length, d128!(201168)),
+	League:             (Length, d128!(4828032)),
 
 	SquareMillimeter:   (Area, d128!(1)),
 	SquareCentimeter:   (Area, d128!(100)),
@@ -272,6 +340,46 @@ create_units!(
 	Volt:                         (Voltage, d128!(1)),
 	Kilovolt:                     (Voltage, d128!(1000)),
 
+	Microcoulomb:                 (ElectricCharge, d128!(0.000001)),
+	Millicoulomb:                 (ElectricCharge, d128!(0.001)),
+	Coulomb:                      (ElectricCharge, d128!(1)),
+	MilliampereHour:              (ElectricCharge, d128!(3.6)),
+	AmpereHour:                   (ElectricCharge, d128!(3600)),
+
+	Picofarad:                    (Capacitance, d128!(0.000000000001)),
+	Nanofarad:                    (Capacitance, d128!(0.000000001)),
+	Microfarad:                   (Capacitance, d128!(0.000001)),
+	Millifarad:                   (Capacitance, d128!(0.001)),
+	Farad:                        (Capacitance, d128!(1)),
+
+	Nanohenry:                    (Inductance, d128!(0.000000001)),
+	Microhenry:                   (Inductance, d128!(0.000001)),
+	Millihenry:                   (Inductance, d128!(0.001)),
+	Henry:                        (Inductance, d128!(1)),
+
+	Microsiemens:                 (Conductance, d128!(0.000001)),
+	Millisiemens:                 (Conductance, d128!(0.001)),
+	Siemens:                      (Conductance, d128!(1)),
+
+	Maxwell:                      (MagneticFlux, d128!(0.00000001)),
+	Milliweber:                   (MagneticFlux, d128!(0.001)),
+	Weber:                        (MagneticFlux, d128!(1)),
+
+	Nanotesla:                    (MagneticFluxDensity, d128!(0.000000001)),
+	Microtesla:                   (MagneticFluxDensity, d128!(0.000001)),
+	Millitesla:                   (MagneticFluxDensity, d128!(0.001)),
+	Gauss:                        (MagneticFluxDensity, d128!(0.0001)),
+	Tesla:                        (MagneticFluxDensity, d128!(1)),
+
+	Newton:                       (Force, d128!(1)),
+	Kilonewton:                   (Force, d128!(1000)),
+	Meganewton:                   (Force, d128!(1000000)),
+	Dyne:                         (Force, d128!(0.00001)),
+	PoundForce:                   (Force, d128!(4.4482216152605)),
+	KilogramForce:                (Force, d128!(9.80665)),
+	Kip:                          (Force, d128!(4448.2216152605)),
+	Poundal:                      (Force, d128!(0.138254954376)),
+
 	Pascal:                       (Pressure, d128!(1)),
 	Kilopascal:                   (Pressure, d128!(1000)),
 	Atmosphere:                   (Pressure, d128!(101325)),
@@ -295,9 +403,60 @@ create_units!(
 	FeetPerSecond:      (Speed, d128!(1.09728)),
 	Knot:               (Speed, d128!(1.852)),
 
+	MetersPerSecondSquared: (Acceleration, d128!(1)),
+	FeetPerSecondSquared:   (Acceleration, d128!(0.3048)),
+	StandardGravity:        (Acceleration, d128!(9.80665)),
+	Gal:                    (Acceleration, d128!(0.01)),
+
+	LitersPerHour:        (VolumetricFlowRate, d128!(1)),
+	LitersPerMinute:      (VolumetricFlowRate, d128!(60)),
+	LitersPerSecond:      (VolumetricFlowRate, d128!(3600)),
+	CubicMetersPerHour:   (VolumetricFlowRate, d128!(1000)),
+	CubicMetersPerSecond: (VolumetricFlowRate, d128!(3600000)),
+	CubicFeetPerMinute:   (VolumetricFlowRate, d128!(1699.01079552)),
+	CubicFeetPerSecond:   (VolumetricFlowRate, d128!(101940.6477312)),
+	GallonsPerHour:       (VolumetricFlowRate, d128!(3.785411784)),
+	GallonsPerMinute:     (VolumetricFlowRate, d128!(227.12470704)),
+
+	GramsPerSecond:       (MassFlowRate, d128!(3.6)),
+	KilogramsPerHour:     (MassFlowRate, d128!(1)),
+	KilogramsPerMinute:   (MassFlowRate, d128!(60)),
+	KilogramsPerSecond:   (MassFlowRate, d128!(3600)),
+	MetricTonsPerHour:    (MassFlowRate, d128!(1000)),
+	PoundsPerHour:        (MassFlowRate, d128!(0.45359237)),
+	PoundsPerMinute:      (MassFlowRate, d128!(27.2155422)),
+	PoundsPerSecond:      (MassFlowRate, d128!(1632.932532)),
+
+	GramsPerLiter:           (Density, d128!(1)),
+	KilogramsPerCubicMeter:  (Density, d128!(1)),
+	KilogramsPerLiter:       (Density, d128!(1000)),
+	GramsPerCubicCentimeter: (Density, d128!(1000)),
+	GramsPerMilliliter:      (Density, d128!(1000)),
+	PoundsPerCubicFoot:      (Density, d128!(16.01846337396013957965507065455659)), // inexact
+	PoundsPerCubicInch:      (Density, d128!(27679.90471020312119364396209107379)), // inexact
+	PoundsPerGallon:         (Density, d128!(119.8264273168966285439132558055142)), // inexact
+
+	KilometersPerLiter:      (FuelEconomy, d128!(1)),
+	MilesPerGallon:          (FuelEconomy, d128!(0.4251437074302720034011496594421760)), // inexact
+	MilesPerImperialGallon:  (FuelEconomy, d128!(0.3540061899346471363303410183256381)), // inexact
+	// l/100km is the reciprocal of the other units, see convert()
+	LitersPer100Kilometers:  (FuelEconomy, d128!(0)),
+
 	Kelvin:             (Temperature, d128!(0)),
 	Celsius:            (Temperature, d128!(0)),
 	Fahrenheit:         (Temperature, d128!(0)),
+
+	Arcsecond:          (Angle, d128!(1)),
+	Arcminute:          (Angle, d128!(60)),
+	Gradian:            (Angle, d128!(3240)),
+	Degree:             (Angle, d128!(3600)),
+	Radian:             (Angle, d128!(206264.8062470963551564733573307786)),
+	Turn:               (Angle, d128!(1296000)),
+
+	// the same value, displayed differently
+	Date:               (Timestamp, d128!(1)),
+	DateTime:           (Timestamp, d128!(1)),
+	UnixTimestamp:      (Timestamp, d128!(1)),
 );
 
 // These functions are here to avoid dividing by small numbers like 0.01,
@@ -318,21 +477,499 @@ fn get_inverted_millivolt_weight() -> d128 {
 	d128!(1000)
 }
 
+/// Every [`UnitType`] that has [`Dimensions`], in the order they're matched
+/// against the result of a multiplication or division
+const DIMENSIONAL_TYPES: [UnitType; 28] = [
+	Time, Length, Area, Volume, Mass, DigitalStorage, DataTransferRate, Energy,
+	Power, ElectricCurrent, Resistance, Voltage, ElectricCharge, Capacitance,
+	Inductance, Conductance, MagneticFlux, MagneticFluxDensity, Force, Pressure,
+	Frequency, Speed, Acceleration, VolumetricFlowRate, MassFlowRate, Density,
+	FuelEconomy, Temperature,
+];
+
+impl UnitType {
+	/// Whether a value of this unit type can be written as several units,
+	/// like `5 ft 7 in` or `2 h 15 min`
+	pub fn allows_mixed_units(&self) -> bool {
+		matches!(self, Time | Length | Mass | Angle)
+	}
+
+	/// The [`Dimensions`] of this unit type, or `None` if it doesn't have any,
+	/// like [`Angle`]
+	pub fn dimensions(&self) -> Option<Dimensions> {
+		let length = Dimensions::LENGTH;
+		let mass = Dimensions::MASS;
+		let time = Dimensions::TIME;
+		let current = Dimensions::CURRENT;
+		let power = mass * length.pow(2) / time.pow(3);
+		let force = mass * length / time.pow(2);
+		let voltage = power / current;
+		let charge = current * time;
+		match self {
+			NoType           => Some(Dimensions::NONE),
+			Time             => Some(time),
+			Length           => Some(length),
+			Area             => Some(length.pow(2)),
+			Volume           => Some(length.pow(3)),
+			Mass             => Some(mass),
+			DigitalStorage   => Some(Dimensions::INFORMATION),
+			DataTransferRate => Some(Dimensions::INFORMATION / time),
+			Energy           => Some(power * time),
+			Power            => Some(power),
+			ElectricCurrent  => Some(current),
+			Resistance       => Some(power / current.pow(2)),
+			Voltage          => Some(voltage),
+			ElectricCharge   => Some(charge),
+			Capacitance      => Some(charge / voltage),
+			Inductance       => Some(voltage * time / current),
+			Conductance      => Some(current.pow(2) / power),
+			MagneticFlux     => Some(voltage * time),
+			MagneticFluxDensity => Some(voltage * time / length.pow(2)),
+			Force            => Some(force),
+			Pressure         => Some(force / length.pow(2)),
+			Frequency        => Some(time.pow(-1)),
+			Speed            => Some(length / time),
+			Acceleration     => Some(length / time.pow(2)),
+			VolumetricFlowRate => Some(length.pow(3) / time),
+			MassFlowRate     => Some(mass / time),
+			Density          => Some(mass / length.pow(3)),
+			FuelEconomy      => Some(length / length.pow(3)),
+			Temperature      => Some(Dimensions::TEMPERATURE),
+			Angle            => None,
+			Derived          => None,
+			Timestamp        => None,
+			UnitType::Currency => None,
+		}
+	}
+
+	/// What the weight of a unit of this type needs to be divided by to get
+	/// the value of the unit in SI base units. For example, [`Length`] weights
+	/// are in millimeters, so this is 1000.
+	fn si_divisor(&self) -> d128 {
+		match self {
+			Time   => d128!(1000000000),
+			Length => d128!(1000),
+			Area   => d128!(1000000),
+			Volume => d128!(1000000000),
+			Mass   => d128!(1000),
+			Speed  => d128!(3.6),
+			VolumetricFlowRate => d128!(3600000),
+			MassFlowRate => d128!(3600),
+			FuelEconomy => d128!(0.000001),
+			_      => d128!(1),
+		}
+	}
+
+	/// The unit a result of this type is created in before it's converted to
+	/// an ideal unit
+	fn si_unit(&self) -> Unit {
+		match self {
+			Time             => Second,
+			Length           => Meter,
+			Area             => SquareMeter,
+			Volume           => CubicMeter,
+			Mass             => Kilogram,
+			DigitalStorage   => Bit,
+			DataTransferRate => BitsPerSecond,
+			Energy           => Joule,
+			Power            => Watt,
+			ElectricCurrent  => Ampere,
+			Resistance       => Ohm,
+			Voltage          => Volt,
+			ElectricCharge   => Coulomb,
+			Capacitance      => Farad,
+			Inductance       => Henry,
+			Conductance      => Siemens,
+			MagneticFlux     => Weber,
+			MagneticFluxDensity => Tesla,
+			Force            => Newton,
+			Pressure         => Pascal,
+			Frequency        => Hertz,
+			Speed            => MetersPerSecond,
+			Acceleration     => MetersPerSecondSquared,
+			VolumetricFlowRate => CubicMetersPerSecond,
+			MassFlowRate     => KilogramsPerSecond,
+			Density          => KilogramsPerCubicMeter,
+			FuelEconomy      => KilometersPerLiter,
+			Temperature      => Kelvin,
+			NoType | Angle | Derived | Timestamp | UnitType::Currency => NoUnit,
+		}
+	}
+}
+
+impl Unit {
+	/// The [`Dimensions`] of this unit, or `None` if it can't be multiplied
+	/// or divided by other units, like [`Degree`] or [`Celsius`]
+	pub fn dimensions(&self) -> Option<Dimensions> {
+		match self {
+			Composite(composite) => composite.dimensions(),
+			// only kelvin has a zero point that allows multiplication
+			Celsius | Fahrenheit => None,
+			// volume per length, so 5 l/100km * 300 km = 15 l
+			LitersPer100Kilometers => Some(Dimensions::LENGTH.pow(2)),
+			unit => unit.category().dimensions(),
+		}
+	}
+
+	/// What a value in this unit needs to be multiplied by to get the value
+	/// in SI base units
+	fn si_factor(&self) -> d128 {
+		match self {
+			Kelvin => d128!(1),
+			LitersPer100Kilometers => d128!(0.00000001),
+			unit => unit.weight() / unit.category().si_divisor(),
+		}
+	}
+}
+
+/// The maximum number of different units a [`CompositeUnit`] can be made of
+const MAX_COMPOSITE_PARTS: usize = 6;
+
+#[derive(Clone, Copy, PartialEq)]
+/// A unit made up of other units with exponents, like `g/cm³`, which is
+/// [`Gram`] to the power of 1 and [`CubicCentimeter`] to the power of -1.
+pub struct CompositeUnit {
+	/// Indices into [`Unit::ALL`] and their exponents. Unused parts have an
+	/// exponent of 0.
+	parts: [(u16, i8); MAX_COMPOSITE_PARTS],
+}
+
+impl CompositeUnit {
+	/// Creates a [`CompositeUnit`] from units and their exponents. Repeated
+	/// units are combined, so `m·m` is `m²`, and composite units are flattened.
+	///
+	/// Returns `None` if it would be made of too many different units, or if
+	/// an exponent of it or its [`Dimensions`] would overflow.
+	pub fn new(units: &[(Unit, i8)]) -> Option<CompositeUnit> {
+		let mut composite = CompositeUnit { parts: [(0, 0); MAX_COMPOSITE_PARTS] };
+		for (unit, exponent) in units {
+			match unit {
+				Composite(inner) => {
+					for (inner_unit, inner_exponent) in inner.parts() {
+						composite.add_part(inner_unit, inner_exponent.checked_mul(*exponent)?)?;
+					}
+				},
+				unit => composite.add_part(*unit, *exponent)?,
+			}
+		}
+		composite.check_dimensions().ok()?;
+		Some(composite)
+	}
+
+	/// Multiplies this unit by `unit` to the power of `exponent`
+	fn add_part(&mut self, unit: Unit, exponent: i8) -> Option<()> {
+		let index = Unit::ALL.iter().position(|u| *u == unit)? as u16;
+		if let Some(part) = self.parts.iter_mut().find(|p| p.0 == index && p.1 != 0) {
+			part.1 = part.1.checked_add(exponent)?;
+		} else if exponent != 0 {
+			let part = self.parts.iter_mut().find(|p| p.1 == 0)?;
+			*part = (index, exponent);
+		}
+		Some(())
+	}
+
+	/// A [`CompositeUnit`] made of the SI base units of some [`Dimensions`],
+	/// like `kg·m/s²`
+	pub fn from_dimensions(dimensions: Dimensions) -> CompositeUnit {
+		// no unit has an amount of substance or luminous intensity yet, so
+		// they can't be part of a result
+		let base_units = [
+			(Kilogram, dimensions.exponents[1]),
+			(Meter, dimensions.exponents[0]),
+			(Second, dimensions.exponents[2]),
+			(Ampere, dimensions.exponents[3]),
+			(Kelvin, dimensions.exponents[4]),
+			(Bit, dimensions.exponents[7]),
+		];
+		CompositeUnit::new(&base_units).unwrap()
+	}
+
+	/// The units this is made of, and their exponents
+	pub fn parts(&self) -> impl Iterator<Item = (Unit, i8)> + '_ {
+		self.parts.iter()
+			.filter(|(_, exponent)| *exponent != 0)
+			.map(|(index, exponent)| (Unit::ALL[*index as usize], *exponent))
+	}
+
+	/// The [`Dimensions`] of this unit, or `None` if one of its parts doesn't
+	/// have any
+	pub fn dimensions(&self) -> Option<Dimensions> {
+		let mut dimensions = Dimensions::NONE;
+		for (unit, exponent) in self.parts() {
+			dimensions = dimensions.checked_mul(unit.dimensions()?.checked_pow(exponent).ok()?).ok()?;
+		}
+		Some(dimensions)
+	}
+
+	/// Returns [`Error::DimensionOverflow`] if an exponent of the
+	/// [`Dimensions`] of this unit would overflow, like in `km¹⁰⁰·m¹⁰⁰`
+	fn check_dimensions(&self) -> Result<(), Error> {
+		let mut dimensions = Dimensions::NONE;
+		for (unit, exponent) in self.parts() {
+			if let Some(unit_dimensions) = unit.dimensions() {
+				dimensions = dimensions.checked_mul(unit_dimensions.checked_pow(exponent)?)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// What a value in this unit needs to be multiplied by to get the value
+	/// in SI base units
+	fn si_factor(&self) -> d128 {
+		let mut factor = d128!(1);
+		for (unit, exponent) in self.parts() {
+			factor *= unit.si_factor().pow(d128::from(exponent as i32));
+		}
+		factor
+	}
+}
+
+impl Display for CompositeUnit {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let numerator: Vec<String> = self.parts()
+			.filter(|(_, exponent)| *exponent > 0)
+			.map(|(unit, exponent)| format_power(&format!("{:?}", unit), exponent.unsigned_abs()))
+			.collect();
+		let denominator: Vec<String> = self.parts()
+			.filter(|(_, exponent)| *exponent < 0)
+			.map(|(unit, exponent)| format_power(&format!("{:?}", unit), exponent.unsigned_abs()))
+			.collect();
+		let numerator = match numerator.len() {
+			0 => "1".to_string(),
+			_ => numerator.join("·"),
+		};
+		match denominator.len() {
+			0 => write!(f, "{}", numerator),
+			1 => write!(f, "{}/{}", numerator, denominator[0]),
+			_ => write!(f, "{}/({})", numerator, denominator.join("·")),
+		}
+	}
+}
+
+impl Debug for CompositeUnit {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		Display::fmt(self, f)
+	}
+}
+
+/// Returns a [`Unit`] made of units and their exponents, like `kg·m/s²`. If
+/// they simplify to a single unit, like `m²/m`, that unit is returned.
+pub fn composite_unit(units: &[(Unit, i8)]) -> Option<Unit> {
+	let composite = CompositeUnit::new(units)?;
+	let mut parts = composite.parts();
+	match (parts.next(), parts.next()) {
+		(None, _) => Some(NoUnit),
+		(Some((unit, 1)), None) => Some(unit),
+		_ => Some(Composite(composite)),
+	}
+}
+
+/// Returns the value of a [`Number`] in SI base units
+pub(crate) fn to_si(number: &Number) -> d128 {
+	number.value * number.unit.si_factor()
+}
+
+/// Speed units and the length units they're made of, used to name results
+const SPEED_LENGTHS: [(Unit, Unit, Unit); 5] = [
+	(KilometersPerHour, Kilometer, Hour),
+	(MetersPerSecond, Meter, Second),
+	(MilesPerHour, Mile, Hour),
+	(FeetPerSecond, Foot, Second),
+	(Knot, NauticalMile, Hour),
+];
+
+/// Data transfer rate units and the digital storage units they're made of,
+/// used to name results
+const DATA_RATES: [(Unit, Unit); 34] = [
+	(BitsPerSecond, Bit),
+	(KilobitsPerSecond, Kilobit),
+	(MegabitsPerSecond, Megabit),
+	(GigabitsPerSecond, Gigabit),
+	(TerabitsPerSecond, Terabit),
+	(PetabitsPerSecond, Petabit),
+	(ExabitsPerSecond, Exabit),
+	(ZettabitsPerSecond, Zettabit),
+	(YottabitsPerSecond, Yottabit),
+	(KibibitsPerSecond, Kibibit),
+	(MebibitsPerSecond, Mebibit),
+	(GibibitsPerSecond, Gibibit),
+	(TebibitsPerSecond, Tebibit),
+	(PebibitsPerSecond, Pebibit),
+	(ExbibitsPerSecond, Exbibit),
+	(ZebibitsPerSecond, Zebibit),
+	(YobibitsPerSecond, Yobibit),
+	(BytesPerSecond, Byte),
+	(KilobytesPerSecond, Kilobyte),
+	(MegabytesPerSecond, Megabyte),
+	(GigabytesPerSecond, Gigabyte),
+	(TerabytesPerSecond, Terabyte),
+	(PetabytesPerSecond, Petabyte),
+	(ExabytesPerSecond, Exabyte),
+	(ZettabytesPerSecond, Zettabyte),
+	(YottabytesPerSecond, Yottabyte),
+	(KibibytesPerSecond, Kibibyte),
+	(MebibytesPerSecond, Mebibyte),
+	(GibibytesPerSecond, Gibibyte),
+	(TebibytesPerSecond, Tebibyte),
+	(PebibytesPerSecond, Pebibyte),
+	(ExbibytesPerSecond, Exbibyte),
+	(ZebibytesPerSecond, Zebibyte),
+	(YobibytesPerSecond, Yobibyte),
+];
+
+/// Units that are one unit divided by another, and the units they're made
+/// of, used to name results
+const QUOTIENT_UNITS: [(Unit, Unit, Unit); 27] = [
+	(LitersPerHour, Liter, Hour),
+	(LitersPerMinute, Liter, Minute),
+	(LitersPerSecond, Liter, Second),
+	(CubicMetersPerHour, CubicMeter, Hour),
+	(CubicMetersPerSecond, CubicMeter, Second),
+	(CubicFeetPerMinute, CubicFoot, Minute),
+	(CubicFeetPerSecond, CubicFoot, Second),
+	(GallonsPerHour, Gallon, Hour),
+	(GallonsPerMinute, Gallon, Minute),
+	(GramsPerSecond, Gram, Second),
+	(KilogramsPerHour, Kilogram, Hour),
+	(KilogramsPerMinute, Kilogram, Minute),
+	(KilogramsPerSecond, Kilogram, Second),
+	(MetricTonsPerHour, MetricTon, Hour),
+	(PoundsPerHour, Pound, Hour),
+	(PoundsPerMinute, Pound, Minute),
+	(PoundsPerSecond, Pound, Second),
+	(GramsPerLiter, Gram, Liter),
+	(KilogramsPerCubicMeter, Kilogram, CubicMeter),
+	(KilogramsPerLiter, Kilogram, Liter),
+	(GramsPerCubicCentimeter, Gram, CubicCentimeter),
+	(GramsPerMilliliter, Gram, Milliliter),
+	(PoundsPerCubicFoot, Pound, CubicFoot),
+	(PoundsPerCubicInch, Pound, CubicInch),
+	(PoundsPerGallon, Pound, Gallon),
+	(KilometersPerLiter, Kilometer, Liter),
+	(MilesPerGallon, Mile, Gallon),
+];
+
+/// Units of charge that are a current times an hour, and that current
+const AMPERE_HOURS: [(Unit, Unit); 2] = [
+	(AmpereHour, Ampere),
+	(MilliampereHour, Milliampere),
+];
+
+/// Returns the unit a result should be in when it's made from units people
+/// expect to see again, like km/h × h = km, or mb / s = mb/s
+fn preferred_unit(category: UnitType, left: Unit, right: Unit) -> Option<Unit> {
+	match (category, left.category(), right.category()) {
+		// 1 km/h * 1 h
+		(Length, Speed, Time) => SPEED_LENGTHS.iter().find(|s| s.0 == left).map(|s| s.1),
+		(Length, Time, Speed) => SPEED_LENGTHS.iter().find(|s| s.0 == right).map(|s| s.1),
+		// 1 km / 2 s
+		(Speed, Length, Time) => {
+			let speed = SPEED_LENGTHS.iter().find(|s| s.1 == left && s.2 == right);
+			Some(speed.map_or(KilometersPerHour, |s| s.0))
+		},
+		// 8 megabytes per second * 1 minute
+		(DigitalStorage, DataTransferRate, Time) => DATA_RATES.iter().find(|d| d.0 == left).map(|d| d.1),
+		(DigitalStorage, Time, DataTransferRate) => DATA_RATES.iter().find(|d| d.0 == right).map(|d| d.1),
+		// 8 megabytes / 1 second
+		(DataTransferRate, DigitalStorage, Time) => DATA_RATES.iter().find(|d| d.1 == left).map(|d| d.0),
+		// 1 newton * 1 meter
+		(Energy, Force, Length) if left == Newton && right == Meter => Some(NewtonMeter),
+		(Energy, Length, Force) if left == Meter && right == Newton => Some(NewtonMeter),
+		// 2 A * 3 h
+		(ElectricCharge, ElectricCurrent, Time) if right == Hour => AMPERE_HOURS.iter().find(|a| a.1 == left).map(|a| a.0),
+		(ElectricCharge, Time, ElectricCurrent) if left == Hour => AMPERE_HOURS.iter().find(|a| a.1 == right).map(|a| a.0),
+		// 3000 mAh / 2 h
+		(ElectricCurrent, ElectricCharge, Time) if right == Hour => AMPERE_HOURS.iter().find(|a| a.0 == left).map(|a| a.1),
+		// 6 l / 100 km
+		(Area, Volume, Length) if left == Liter && right == Kilometer => Some(LitersPer100Kilometers),
+		_ => preferred_quotient_unit(category, left, right),
+	}
+}
+
+/// Like [`preferred_unit`], for the units in [`QUOTIENT_UNITS`]
+fn preferred_quotient_unit(category: UnitType, left: Unit, right: Unit) -> Option<Unit> {
+	QUOTIENT_UNITS.iter().find_map(|&(quotient, numerator, denominator)| {
+		if category == quotient.category() && left == numerator && right == denominator {
+			// 10 l / 1 min
+			Some(quotient)
+		} else if category == numerator.category() && (
+			(left == quotient && right.category() == denominator.category())
+			|| (right == quotient && left.category() == denominator.category())
+		) {
+			// 5 l/min * 10 min, 2 g/cm3 * 5 cm3
+			Some(numerator)
+		} else if category == denominator.category() && left.category() == numerator.category() && right == quotient {
+			// 10 kg / 2 kg/l
+			Some(denominator)
+		} else {
+			None
+		}
+	})
+}
+
+/// Turns a value in SI base units with some [`Dimensions`] into a [`Number`].
+///
+/// If there's a [`UnitType`] with those dimensions, the result gets a unit of
+/// that type, preferably one made from `left` and `right`, otherwise an ideal
+/// unit. If not, the result is a [`Composite`] unit.
+fn from_si(value: d128, dimensions: Dimensions, left: Unit, right: Unit) -> Number {
+	if dimensions.is_dimensionless() {
+		return Number::new(value, NoUnit);
+	}
+	let category = match DIMENSIONAL_TYPES.iter().find(|t| t.dimensions() == Some(dimensions)) {
+		Some(category) => *category,
+		None => return Number::new(value, Composite(CompositeUnit::from_dimensions(dimensions))),
+	};
+	if let Some(unit) = preferred_unit(category, left, right) {
+		return Number::new(value / unit.si_factor(), unit);
+	}
+	if category == Energy && (left == Second || right == Second) {
+		// 1 watt * 1 second = 1 joule, but 1 watt * 1 hour = 1 watt hour
+		return to_ideal_joule_unit(Number::new(value, Joule));
+	}
+	if category == Energy && (left.category() == Force || right.category() == Force) {
+		// 1 kilonewton * 1 kilometer = 1 megajoule
+		return to_ideal_joule_unit(Number::new(value, Joule));
+	}
+	let unit = category.si_unit();
+	to_ideal_unit(Number::new(value / unit.si_factor(), unit))
+}
+
 /// Returns the conversion factor between two units.
 /// 
 /// The conversion factor is what you need to multiply `unit` with to get
 /// `to_unit`. For example, the conversion factor from 1 minute to 1 second
 /// is 60.
 /// 
-/// This is not sufficient for [`Temperature`] units.
+/// This is not sufficient for [`Temperature`] units, or for
+/// [`LitersPer100Kilometers`].
 pub fn get_conversion_factor(unit: Unit, to_unit: Unit) -> d128 {
 	unit.weight() / to_unit.weight()
 }
 
+/// Returns true if `unit` can be converted to `to_unit`, which is when they
+/// have the same [`UnitType`] or the same [`Dimensions`]
+fn can_convert(unit: Unit, to_unit: Unit) -> bool {
+	if unit.category() == to_unit.category() && unit.category() != Derived {
+		return true;
+	}
+	match (unit.dimensions(), to_unit.dimensions()) {
+		(Some(dimensions), Some(to_dimensions)) => dimensions == to_dimensions,
+		_ => false,
+	}
+}
+
 /// Convert a [`Number`] to a specified [`Unit`].
-pub fn convert(number: Number, to_unit: Unit) -> Result<Number, String> {
-	if number.unit.category() != to_unit.category() {
-		return Err(format!("Cannot convert from {:?} to {:?}", number.unit, to_unit));
+///
+/// Units of different [`UnitType`]s can be converted if they have the same
+/// [`Dimensions`], like a [`Composite`] of kg/(m·s²) to [`Pascal`].
+pub fn convert(number: Number, to_unit: Unit) -> Result<Number, Error> {
+	if !can_convert(number.unit, to_unit) {
+		return Err(Error::CannotConvert { from: number.unit, to: to_unit, span: None });
+	}
+	if number.unit.category() != to_unit.category() || number.unit.category() == Derived {
+		let value = to_si(&number) / to_unit.si_factor();
+		return Ok(Number::new(value, to_unit));
 	}
 	let value = number.value;
 	let ok = |new_value| {
@@ -349,7 +986,29 @@ pub fn convert(number: Number, to_unit: Unit) -> Result<Number, String> {
 			(Fahrenheit, Fahrenheit) => ok(value),
 			(Fahrenheit, Kelvin)     => ok((value+d128!(459.67))*d128!(5)/d128!(9)),
 			(Fahrenheit, Celsius)    => ok((value-d128!(32))/d128!(1.8)),
-			_ => Err(format!("Error converting temperature {:?} to {:?}", number.unit, to_unit)),
+			_ => Err(Error::CannotConvert { from: number.unit, to: to_unit, span: None }),
+		}
+	} else if number.unit.category() == FuelEconomy {
+		// l/100km is the reciprocal of the other units
+		let reciprocal = (number.unit == LitersPer100Kilometers) != (to_unit == LitersPer100Kilometers);
+		if reciprocal && value <= d128!(0) {
+			return Err(Error::InvalidFuelEconomy { value, span: None });
+		}
+		match (number.unit, to_unit) {
+			(LitersPer100Kilometers, LitersPer100Kilometers) => ok(value),
+			(LitersPer100Kilometers, _) => ok(d128!(100) / value / to_unit.weight()),
+			(_, LitersPer100Kilometers) => ok(d128!(100) / (value * number.unit.weight())),
+			_ => ok(value * get_conversion_factor(number.unit, to_unit)),
+		}
+	} else if number.unit.category() == UnitType::Currency && number.unit != to_unit {
+		// the rates are used instead of the weights, since 1/rate is rounded
+		match (number.unit, to_unit) {
+			(Unit::Currency(currency), Unit::Currency(to_currency)) => match (currency.rate(), to_currency.rate()) {
+				(Some(rate), Some(to_rate)) => ok(value / rate * to_rate),
+				(None, _) => Err(Error::MissingExchangeRate { currency, span: None }),
+				(_, None) => Err(Error::MissingExchangeRate { currency: to_currency, span: None }),
+			},
+			_ => Err(Error::CannotConvert { from: number.unit, to: to_unit, span: None }),
 		}
 	} else {
 		let conversion_factor = get_conversion_factor(number.unit, to_unit);
@@ -359,8 +1018,12 @@ pub fn convert(number: Number, to_unit: Unit) -> Result<Number, String> {
 
 /// If one of two provided [`Number`]s has a larger [`Unit`] than the other, convert
 /// the large one to the unit of the small one.
-pub fn convert_to_lowest(left: Number, right: Number) -> Result<(Number, Number), String> {
-	if left.unit.weight() == right.unit.weight() {
+pub fn convert_to_lowest(left: Number, right: Number) -> Result<(Number, Number), Error> {
+	if left.unit.category() != right.unit.category() {
+		// weights of different unit types can't be compared
+		let right_converted = convert(right, left.unit)?;
+		Ok((left, right_converted))
+	} else if left.unit.weight() == right.unit.weight() {
 		Ok((left, right))
 	} else if left.unit.weight() > right.unit.weight() {
 		let left_converted = convert(left, right.unit)?;
@@ -371,27 +1034,52 @@ pub fn convert_to_lowest(left: Number, right: Number) -> Result<(Number, Number)
 	}
 }
 
+/// An [`Error::IncompatibleUnits`] without a span, which the evaluator fills in
+fn incompatible(left: Unit, right: Unit, op: Operator) -> Error {
+	Error::IncompatibleUnits { left, right, op, span: None }
+}
+
 /// Return the sum of two [`Number`]s
-pub fn add(left: Number, right: Number) -> Result<Number, String> {
-	if left.unit == right.unit {
+///
+/// A [`Time`] can be added to a [`Timestamp`], see [`datetime::add_duration`]
+pub fn add(left: Number, right: Number) -> Result<Number, Error> {
+	if datetime::is_timestamp(left.unit) || datetime::is_timestamp(right.unit) {
+		match (left.unit.category(), right.unit.category()) {
+			// 2026-03-01 + 90 days
+			(Timestamp, Time) => datetime::add_duration(left, right),
+			(Time, Timestamp) => datetime::add_duration(right, left),
+			_ => Err(incompatible(left.unit, right.unit, Operator::Plus)),
+		}
+	} else if left.unit == right.unit {
 		Ok(Number::new(left.value + right.value, left.unit))
-	} else if left.unit.category() == right.unit.category() && left.unit.category() != Temperature {
+	} else if can_convert(left.unit, right.unit) && left.unit.category() != Temperature {
 		let (left, right) = convert_to_lowest(left, right)?;
 		Ok(Number::new(left.value + right.value, left.unit))
 	} else {
-		Err(format!("Cannot add {:?} and {:?}", left.unit, right.unit))
+		Err(incompatible(left.unit, right.unit, Operator::Plus))
 	}
 }
 
 /// Subtract a [`Number`] from another [`Number`]
-pub fn subtract(left: Number, right: Number) -> Result<Number, String> {
-	if left.unit == right.unit {
+///
+/// A [`Time`] can be subtracted from a [`Timestamp`], and subtracting two
+/// [`Timestamp`]s returns the [`Time`] between them
+pub fn subtract(left: Number, right: Number) -> Result<Number, Error> {
+	if datetime::is_timestamp(left.unit) || datetime::is_timestamp(right.unit) {
+		match (left.unit.category(), right.unit.category()) {
+			// 2026-12-25 - 2026-10-18
+			(Timestamp, Timestamp) => Ok(datetime::difference(left, right)),
+			// 2026-03-01 - 2 weeks
+			(Timestamp, Time) => datetime::add_duration(left, Number::new(-right.value, right.unit)),
+			_ => Err(incompatible(left.unit, right.unit, Operator::Minus)),
+		}
+	} else if left.unit == right.unit {
 		Ok(Number::new(left.value - right.value, left.unit))
-	} else if left.unit.category() == right.unit.category() && left.unit.category() != Temperature {
+	} else if can_convert(left.unit, right.unit) && left.unit.category() != Temperature {
 		let (left, right) = convert_to_lowest(left, right)?;
 		Ok(Number::new(left.value - right.value, left.unit))
 	} else {
-		Err(format!("Cannot subtract {:?} by {:?}", left.unit, right.unit))
+		Err(incompatible(left.unit, right.unit, Operator::Minus))
 	}
 }
 
@@ -400,19 +1088,29 @@ pub fn subtract(left: Number, right: Number) -> Result<Number, String> {
 /// If you have 1,000,000 millimeters, this will return 1 kilometer.
 /// 
 /// This only affects units of `Length`, `Time`, `Area`, `Volume`,
-/// `Energy`, `Power`, `ElectricCurrent`, `Resistance`, and `Voltage`.
+/// `Energy`, `Power`, `ElectricCurrent`, `Resistance`, `Voltage`, `Force`,
+/// `ElectricCharge`, `Capacitance`, `Inductance`, `Conductance`,
+/// `MagneticFlux` and `MagneticFluxDensity`.
 /// Other units are passed through.
 pub fn to_ideal_unit(number: Number) -> Number {
 	let value = number.value * number.unit.weight();
 	if number.unit.category() == Length {
 		if value >= d128!(1000000000000000000) { // ≈ 0.1 light years
-			return Number::new(value/Kilometer.weight(), Kilometer)
+			return Number::new(value/LightYear.weight(), LightYear)
+		} else if value >= d128!(149597870700000) { // 1 AU
+			return Number::new(value/AstronomicalUnit.weight(), AstronomicalUnit)
 		} else if value >= d128!(1000000) { // 1 km
 			return Number::new(value/Kilometer.weight(), Kilometer)
 		} else if value >= d128!(1000) { // 1 m
 			return Number::new(value/Meter.weight(), Meter)
 		} else if value >= d128!(10) { // 1 cm
 			return Number::new(value/Centimeter.weight(), Centimeter)
+		} else if value >= d128!(1) { // 1 mm
+			return Number::new(value, Millimeter)
+		} else if value >= d128!(0.001) { // 1 µm
+			return Number::new(value/Micrometer.weight(), Micrometer)
+		} else if value > d128!(0) {
+			return Number::new(value/Nanometer.weight(), Nanometer)
 		} else {
 			return Number::new(value, Millimeter)
 		}
@@ -516,6 +1214,68 @@ pub fn to_ideal_unit(number: Number) -> Number {
 		} else {
 			return Number::new(value * get_inverted_millivolt_weight(), Millivolt)
 		}
+	} else if number.unit.category() == Force {
+		if value >= d128!(1000000) { // 1 meganewton
+			return Number::new(value/Meganewton.weight(), Meganewton)
+		} else if value >= d128!(1000) { // 1 kilonewton
+			return Number::new(value/Kilonewton.weight(), Kilonewton)
+		} else {
+			return Number::new(value, Newton)
+		}
+	} else if number.unit.category() == ElectricCharge {
+		if value >= d128!(1) { // 1 coulomb
+			return Number::new(value, Coulomb)
+		} else if value >= d128!(0.001) { // 1 millicoulomb
+			return Number::new(value/Millicoulomb.weight(), Millicoulomb)
+		} else {
+			return Number::new(value/Microcoulomb.weight(), Microcoulomb)
+		}
+	} else if number.unit.category() == Capacitance {
+		if value >= d128!(1) { // 1 farad
+			return Number::new(value, Farad)
+		} else if value >= d128!(0.001) { // 1 millifarad
+			return Number::new(value/Millifarad.weight(), Millifarad)
+		} else if value >= d128!(0.000001) { // 1 microfarad
+			return Number::new(value/Microfarad.weight(), Microfarad)
+		} else if value >= d128!(0.000000001) { // 1 nanofarad
+			return Number::new(value/Nanofarad.weight(), Nanofarad)
+		} else {
+			return Number::new(value/Picofarad.weight(), Picofarad)
+		}
+	} else if number.unit.category() == Inductance {
+		if value >= d128!(1) { // 1 henry
+			return Number::new(value, Henry)
+		} else if value >= d128!(0.001) { // 1 millihenry
+			return Number::new(value/Millihenry.weight(), Millihenry)
+		} else if value >= d128!(0.000001) { // 1 microhenry
+			return Number::new(value/Microhenry.weight(), Microhenry)
+		} else {
+			return Number::new(value/Nanohenry.weight(), Nanohenry)
+		}
+	} else if number.unit.category() == Conductance {
+		if value >= d128!(1) { // 1 siemens
+			return Number::new(value, Siemens)
+		} else if value >= d128!(0.001) { // 1 millisiemens
+			return Number::new(value/Millisiemens.weight(), Millisiemens)
+		} else {
+			return Number::new(value/Microsiemens.weight(), Microsiemens)
+		}
+	} else if number.unit.category() == MagneticFlux {
+		if value >= d128!(1) { // 1 weber
+			return Number::new(value, Weber)
+		} else {
+			return Number::new(value/Milliweber.weight(), Milliweber)
+		}
+	} else if number.unit.category() == MagneticFluxDensity {
+		if value >= d128!(1) { // 1 tesla
+			return Number::new(value, Tesla)
+		} else if value >= d128!(0.001) { // 1 millitesla
+			return Number::new(value/Millitesla.weight(), Millitesla)
+		} else if value >= d128!(0.000001) { // 1 microtesla
+			return Number::new(value/Microtesla.weight(), Microtesla)
+		} else {
+			return Number::new(value/Nanotesla.weight(), Nanotesla)
+		}
 	}
 	number
 }
@@ -543,202 +1303,78 @@ pub fn to_ideal_joule_unit(number: Number) -> Number {
 
 /// Multiply two [`Number`]s
 /// 
-/// - Temperatures don't work
+/// - Temperatures don't work, except for [`Kelvin`]
 /// - If you multiply [`NoType`] with any other unit, the result gets that other unit
-/// - If you multiply [`Length`] with [`Length`], the result has a unit of [`Area`], etc.
-/// - If you multiply [`Speed`] with [`Time`], the result has a unit of [`Length`]
-/// - If you multiply [`Voltage`] with [`ElectricCurrent`], the result has a unit of [`Power`]
-/// - If you multiply [`ElectricCurrent`] with [`Resistance`], the result has a unit of [`Voltage`]
-/// - If you multiply [`Power`] with [`Time`], the result has a unit of [`Energy`]
-pub fn multiply(left: Number, right: Number) -> Result<Number, String> {
-	actual_multiply(left, right, false)
-}
-
-fn actual_multiply(left: Number, right: Number, swapped: bool) -> Result<Number, String> {
-	let lcat = left.unit.category();
-	let rcat = right.unit.category();
+/// - Otherwise, the [`Dimensions`] of the units are multiplied, so [`Length`]
+///   times [`Length`] has a unit of [`Area`], [`Voltage`] times
+///   [`ElectricCurrent`] has a unit of [`Power`], etc.
+/// - If no [`UnitType`] has the resulting [`Dimensions`], the result has a
+///   [`Composite`] unit
+pub fn multiply(left: Number, right: Number) -> Result<Number, Error> {
 	if left.unit == NoUnit && right.unit == NoUnit {
 		// 3 * 2
 		Ok(Number::new(left.value * right.value, left.unit))
-	} else if left.unit.category() == Temperature || right.unit.category() == Temperature {
-		// if temperature
-		Err(format!("Cannot multiply {:?} and {:?}", left.unit, right.unit))
-	} else if left.unit == NoUnit && right.unit != NoUnit {
+	} else if matches!(left.unit, Celsius | Fahrenheit) || matches!(right.unit, Celsius | Fahrenheit) {
+		// if celsius or fahrenheit
+		Err(incompatible(left.unit, right.unit, Operator::Multiply))
+	} else if datetime::is_timestamp(left.unit) || datetime::is_timestamp(right.unit) {
+		// 2 * 2026-03-01
+		Err(incompatible(left.unit, right.unit, Operator::Multiply))
+	} else if left.unit == NoUnit {
 		// 3 * 2 anyunit
 		Ok(Number::new(left.value * right.value, right.unit))
-	} else if lcat == Length && rcat == Length {
-		// length * length
-		let result = (left.value * left.unit.weight()) * (right.value * right.unit.weight());
-		Ok(to_ideal_unit(Number::new(result, SquareMillimeter)))
-	} else if lcat == Length && rcat == Area {
-		// length * area
-		let result = (left.value * left.unit.weight()) * (right.value * right.unit.weight());
-		Ok(to_ideal_unit(Number::new(result, CubicMillimeter)))
-	} else if lcat == Speed && rcat == Time {
-		// 1 km/h * 1h
-		let kph_value = left.value * left.unit.weight();
-		let hours = convert(right, Hour)?;
-		let result = kph_value * hours.value;
-		let final_unit = match left.unit {
-			KilometersPerHour => Kilometer,
-			MetersPerSecond => Meter,
-			MilesPerHour => Mile,
-			FeetPerSecond => Foot,
-			Knot => NauticalMile,
-			_ => Meter,
-		};
-		let kilometers = Number::new(result, Kilometer);
-		Ok(convert(kilometers, final_unit)?)
-	} else if lcat == DataTransferRate && rcat == Time {
-		// 8 megabytes per second * 1 minute
-		let data_rate_value = left.value * left.unit.weight();
-		let seconds = convert(right, Second)?;
-		let result = data_rate_value * seconds.value;
-		let final_unit = match left.unit {
-			BitsPerSecond => Bit,
-			KilobitsPerSecond => Kilobit,
-			MegabitsPerSecond => Megabit,
-			GigabitsPerSecond => Gigabit,
-			TerabitsPerSecond => Terabit,
-			PetabitsPerSecond => Petabit,
-			ExabitsPerSecond => Exabit,
-			ZettabitsPerSecond => Zettabit,
-			YottabitsPerSecond => Yottabit,
-			KibibitsPerSecond => Kibibit,
-			MebibitsPerSecond => Mebibit,
-			GibibitsPerSecond => Gibibit,
-			TebibitsPerSecond => Tebibit,
-			PebibitsPerSecond => Pebibit,
-			ExbibitsPerSecond => Exbibit,
-			ZebibitsPerSecond => Zebibit,
-			YobibitsPerSecond => Yobibit,
-			BytesPerSecond => Byte,
-			KilobytesPerSecond => Kilobyte,
-			MegabytesPerSecond => Megabyte,
-			GigabytesPerSecond => Gigabyte,
-			TerabytesPerSecond => Terabyte,
-			PetabytesPerSecond => Petabyte,
-			ExabytesPerSecond => Exabyte,
-			ZettabytesPerSecond => Zettabyte,
-			YottabytesPerSecond => Yottabyte,
-			KibibytesPerSecond => Kibibyte,
-			MebibytesPerSecond => Mebibyte,
-			GibibytesPerSecond => Gibibyte,
-			TebibytesPerSecond => Tebibyte,
-			PebibytesPerSecond => Pebibyte,
-			ExbibytesPerSecond => Exbibyte,
-			ZebibytesPerSecond => Zebibyte,
-			YobibytesPerSecond => Yobibyte,
-			_ => Bit,
-		};
-		let data_storage = Number::new(result, Bit);
-		Ok(convert(data_storage, final_unit)?)
-	} else if lcat == Voltage && rcat == ElectricCurrent {
-		// 1 volt * 1 ampere = 1 watt
-		let result = (left.value * left.unit.weight()) * (right.value * right.unit.weight());
-		Ok(to_ideal_unit(Number::new(result, Watt)))
-	} else if lcat == ElectricCurrent && rcat == Resistance {
-		// 1 amp * 1 ohm = 1 volt
-		let result = (left.value * left.unit.weight()) * (right.value * right.unit.weight());
-		Ok(to_ideal_unit(Number::new(result, Watt)))
-	} else if lcat == Power && rcat == Time {
-		// 1 watt * 1 second = 1 joule
-		let result = (left.value * left.unit.weight()) * (right.value * right.unit.weight() / Unit::Second.weight());
-		match right.unit {
-			Second => Ok(to_ideal_joule_unit(Number::new(result, Joule))),
-			_ => Ok(to_ideal_unit(Number::new(result, Joule))),
-		}
-	} else if swapped {
-		Err(format!("Cannot multiply {:?} and {:?}", right.unit, left.unit))
+	} else if right.unit == NoUnit {
+		// 2 anyunit * 3
+		Ok(Number::new(left.value * right.value, left.unit))
 	} else {
-		actual_multiply(right, left, true)
+		match (left.unit.dimensions(), right.unit.dimensions()) {
+			(Some(left_dimensions), Some(right_dimensions)) => {
+				// 1 km * 1 km, 1 volt * 1 ampere
+				let value = to_si(&left) * to_si(&right);
+				Ok(from_si(value, left_dimensions.checked_mul(right_dimensions)?, left.unit, right.unit))
+			},
+			_ => Err(incompatible(left.unit, right.unit, Operator::Multiply)),
+		}
 	}
 }
 
 /// Divide a [`Number`] by another [`Number`]
 /// 
-/// - Temperatures don't work
+/// - Temperatures don't work, except for [`Kelvin`]
 /// - If you divide a unit by that same unit, the result has a unit of [`NoType`]
-/// - If you divide [`Volume`] by [`Length`], the result has a unit of [`Area`], etc.
-/// - If you divide [`Length`] by [`Time`], the result has a unit of [`Speed`]
-/// - If you divide [`Length`] by [`Speed`], the result has a unit of [`Time`]
-/// - If you divide [`Power`] by [`ElectricCurrent`], the result has a unit of [`Volt`]
-/// - If you divide [`Voltage`] by [`ElectricCurrent`], the result has a unit of [`Ohm`]
-/// - If you divide [`Voltage`] by [`Resistance`], the result has a unit of [`Ampere`]
-/// - If you divide [`Power`] by [`Voltage`], the result has a unit of [`Ampere`]
-/// - If you divide [`Energy`] by [`Time`], the result has a unit of [`Power`]
-pub fn divide(left: Number, right: Number) -> Result<Number, String> {
+/// - Otherwise, the [`Dimensions`] of the units are divided, so [`Length`]
+///   divided by [`Time`] has a unit of [`Speed`], [`Voltage`] divided by
+///   [`Resistance`] has a unit of [`ElectricCurrent`], etc.
+/// - If no [`UnitType`] has the resulting [`Dimensions`], the result has a
+///   [`Composite`] unit
+pub fn divide(left: Number, right: Number) -> Result<Number, Error> {
 	let lcat = left.unit.category();
 	let rcat = right.unit.category();
 	if left.unit == NoUnit && right.unit == NoUnit {
 		// 3 / 2
 		Ok(Number::new(left.value / right.value, left.unit))
-	} else if lcat == Temperature || rcat == Temperature {
-		// if temperature
-		Err(format!("Cannot divide {:?} by {:?}", left.unit, right.unit))
+	} else if matches!(left.unit, Celsius | Fahrenheit) || matches!(right.unit, Celsius | Fahrenheit) {
+		// if celsius or fahrenheit
+		Err(incompatible(left.unit, right.unit, Operator::Divide))
+	} else if datetime::is_timestamp(left.unit) || datetime::is_timestamp(right.unit) {
+		// 2026-03-01 / 2
+		Err(incompatible(left.unit, right.unit, Operator::Divide))
 	} else if left.unit != NoUnit && right.unit == NoUnit {
 		// 1 km / 2
 		Ok(Number::new(left.value / right.value, left.unit))
-	} else if lcat == rcat {
+	} else if lcat == rcat && lcat != Derived {
 		// 4 km / 2 km
 		let (left, right) = convert_to_lowest(left, right)?;
 		Ok(Number::new(left.value / right.value, NoUnit))
-	} else if (lcat == Area && rcat == Length) || (lcat == Volume && rcat == Area) {
-		// 1 km2 / 1 km, 1 km3 / 1 km2
-		let result = (left.value * left.unit.weight()) / (right.value * right.unit.weight());
-		Ok(to_ideal_unit(Number::new(result, Millimeter)))
-	} else if lcat == Volume && rcat == Length {
-		// 1 km3 / 1 km
-		let result = (left.value * left.unit.weight()) / (right.value * right.unit.weight());
-		Ok(to_ideal_unit(Number::new(result, SquareMillimeter)))
-	} else if lcat == Length && rcat == Time {
-		// 1 km / 2s
-		let final_unit = match (left.unit, right.unit) {
-			(Kilometer, Hour) => KilometersPerHour,
-			(Meter, Second) => MetersPerSecond,
-			(Mile, Hour) => MilesPerHour,
-			(Foot, Second) => FeetPerSecond,
-			(NauticalMile, Hour) => Knot,
-			_ => KilometersPerHour,
-		};
-		let kilometers = convert(left, Kilometer)?;
-		let hours = convert(right, Hour)?;
-		let kph = Number::new(kilometers.value / hours.value, KilometersPerHour);
-		Ok(convert(kph, final_unit)?)
-	} else if lcat == Length && rcat == Speed {
-		// 12 km / 100 kph
-		let kilometers = convert(left, Kilometer)?;
-		let kilometers_per_hour = convert(right, KilometersPerHour)?;
-		let hour = Number::new(kilometers.value / kilometers_per_hour.value, Hour);
-		Ok(to_ideal_unit(hour))
-	} else if lcat == DigitalStorage && rcat == DataTransferRate {
-		// 1 kilobit / 1 bit per second
-		let bits = convert(left, Bit)?;
-		let bits_per_second = convert(right, BitsPerSecond)?;
-		let seconds = Number::new(bits.value / bits_per_second.value, Second);
-		Ok(to_ideal_unit(seconds))
-	} else if lcat == Power && rcat == ElectricCurrent {
-		// 1 watt / 1 ampere = 1 volt
-		let result = (left.value * left.unit.weight()) / (right.value * right.unit.weight());
-		Ok(to_ideal_unit(Number::new(result, Volt)))
-	} else if lcat == Voltage && rcat == ElectricCurrent {
-		// 1 volt / 1 ampere = 1 ohm
-		let result = (left.value * left.unit.weight()) / (right.value * right.unit.weight());
-		Ok(to_ideal_unit(Number::new(result, Ohm)))
-	} else if lcat == Voltage && rcat == Resistance {
-		// 1 volt / 1 ohm = 1 amp
-		let result = (left.value * left.unit.weight()) / (right.value * right.unit.weight());
-		Ok(to_ideal_unit(Number::new(result, Ampere)))
-	} else if lcat == Power && rcat == Voltage {
-		// 1 watt / 1 volt = 1 amp
-		let result = (left.value * left.unit.weight()) / (right.value * right.unit.weight());
-		Ok(to_ideal_unit(Number::new(result, Ampere)))
-	} else if lcat == Energy && rcat == Time {
-		// 1 joule / 1 second = 1 watt
-		let result = (left.value * left.unit.weight()) / (right.value * right.unit.weight() / Unit::Second.weight());
-		Ok(to_ideal_unit(Number::new(result, Watt)))
 	} else {
-		Err(format!("Cannot divide {:?} by {:?}", left.unit, right.unit))
+		match (left.unit.dimensions(), right.unit.dimensions()) {
+			(Some(left_dimensions), Some(right_dimensions)) => {
+				// 1 km / 2 s, 1 watt / 1 volt
+				let value = to_si(&left) / to_si(&right);
+				Ok(from_si(value, left_dimensions.checked_div(right_dimensions)?, left.unit, right.unit))
+			},
+			_ => Err(incompatible(left.unit, right.unit, Operator::Divide)),
+		}
 	}
 }
 /// Modulo a [`Number`] by another [`Number`].
@@ -746,41 +1382,60 @@ pub fn divide(left: Number, right: Number) -> Result<Number, String> {
 /// `left` and `right` need to have the same [`UnitType`], and the result will have that same [`UnitType`].
 ///
 /// Temperatures don't work.
-pub fn modulo(left: Number, right: Number) -> Result<Number, String> {
+pub fn modulo(left: Number, right: Number) -> Result<Number, Error> {
 	if left.unit.category() == Temperature || right.unit.category() == Temperature {
 		// if temperature
-		Err(format!("Cannot modulo {:?} by {:?}", left.unit, right.unit))
-	} else if left.unit.category() == right.unit.category() {
+		Err(incompatible(left.unit, right.unit, Operator::Modulo))
+	} else if datetime::is_timestamp(left.unit) || datetime::is_timestamp(right.unit) {
+		Err(incompatible(left.unit, right.unit, Operator::Modulo))
+	} else if can_convert(left.unit, right.unit) {
 		// 5 km % 3 m
 		let (left, right) = convert_to_lowest(left, right)?;
 		Ok(Number::new(left.value % right.value, left.unit))
 	} else {
-		Err(format!("Cannot modulo {:?} by {:?}", left.unit, right.unit))
+		Err(incompatible(left.unit, right.unit, Operator::Modulo))
 	}
 }
 
 /// Returns a [`Number`] to the power of another [`Number`]
 /// 
-/// - If you take [`Length`] to the power of [`NoType`], the result has a unit of [`Area`].
+/// - If you take a unit to the power of a number, its [`Dimensions`] are
+///   raised to that power, so [`Length`] ^ 2 has a unit of [`Area`], [`Time`]
+///   ^ -1 has a unit of [`Frequency`] and [`Area`] ^ 0.5 has a unit of
+///   [`Length`]. The resulting dimensions need to be integers. Odd roots of
+///   negative values are negative, like [`root`].
 /// - If you take [`Length`] to the power of [`Length`], the result has a unit of [`Area`]
 /// - If you take [`Length`] to the power of [`Area`], the result has a unit of [`Volume`]
 /// - etc.
-pub fn pow(left: Number, right: Number) -> Result<Number, String> {
+pub fn pow(left: Number, right: Number) -> Result<Number, Error> {
 	let lcat = left.unit.category();
-	let rcat = left.unit.category();
+	let rcat = right.unit.category();
 	if left.unit == NoUnit && right.unit == NoUnit {
 		// 3 ^ 2
 		Ok(Number::new(left.value.pow(right.value), left.unit))
 	} else if right.value == d128!(1) && right.unit == NoUnit {
 		Ok(left)
-	} else if lcat == Length && right.unit == NoUnit && right.value == d128!(2) {
-		// x km ^ 2
-		let result = (left.value * left.unit.weight()).pow(right.value);
-		Ok(to_ideal_unit(Number::new(result, SquareMillimeter)))
-	} else if lcat == Length && right.unit == NoUnit && right.value == d128!(3) {
-		// x km ^ 3
-		let result = (left.value * left.unit.weight()).pow(right.value);
-		Ok(to_ideal_unit(Number::new(result, CubicMillimeter)))
+	} else if let (Some(dimensions), NoUnit) = (left.unit.dimensions(), right.unit) {
+		// x km ^ 2, x s ^ -2, x km2 ^ 0.5
+		let fractional = || Error::FractionalDimensions { unit: left.unit, exponent: right.value, span: None };
+		let (numerator, denominator) = match as_fraction(right.value) {
+			Some(fraction) => fraction,
+			// an integer exponent too large for a unit, like x m ^ 200
+			None if to_integer(right.value).is_some() => return Err(Error::DimensionOverflow { span: None }),
+			None => return Err(fractional()),
+		};
+		let dimensions = dimensions.root(denominator).ok_or_else(fractional)?.checked_pow(numerator)?;
+		let si_value = to_si(&left);
+		// like root(), odd roots of negative values are negative
+		if si_value.is_negative() && denominator % 2 == 0 {
+			return Err(Error::DomainError { function: FunctionIdentifier::Root, value: left.value, span: None });
+		}
+		let magnitude = match denominator {
+			1 => si_value.abs().pow(d128::from(numerator as i32)),
+			_ => evaluator::root(si_value.abs(), d128::from(denominator as i32)).pow(d128::from(numerator as i32)),
+		};
+		let result = if si_value.is_negative() && numerator % 2 != 0 { -magnitude } else { magnitude };
+		Ok(power_from_si(result, dimensions, left.unit))
 	} else if lcat == Length && rcat == Length && right.value == d128!(1) {
 		// x km ^ 1 km
 		Ok(multiply(left, right)?)
@@ -793,10 +1448,261 @@ pub fn pow(left: Number, right: Number) -> Result<Number, String> {
 		// x km ^ km2
 		Ok(multiply(left, Number::new(d128!(1), right.unit))?)
 	} else {
-		Err(format!("Cannot multiply {:?} and {:?}", left.unit, right.unit))
+		Err(incompatible(left.unit, right.unit, Operator::Caret))
 	}
 }
 
+/// Units of area and volume that are a unit of length squared or cubed, and
+/// that unit of length
+const POWER_UNITS: [(Unit, Unit); 20] = [
+	(SquareMillimeter, Millimeter),
+	(SquareCentimeter, Centimeter),
+	(SquareDecimeter, Decimeter),
+	(SquareMeter, Meter),
+	(SquareKilometer, Kilometer),
+	(SquareInch, Inch),
+	(SquareFoot, Foot),
+	(SquareYard, Yard),
+	(SquareMile, Mile),
+	(CubicMillimeter, Millimeter),
+	(CubicCentimeter, Centimeter),
+	(CubicDecimeter, Decimeter),
+	(CubicMeter, Meter),
+	(CubicKilometer, Kilometer),
+	(CubicInch, Inch),
+	(CubicFoot, Foot),
+	(CubicYard, Yard),
+	(CubicMile, Mile),
+	(Milliliter, Centimeter),
+	(Liter, Decimeter),
+];
+
+/// Turns the result of raising `unit` to a power into a [`Number`], like
+/// [`from_si`]. Powers and roots of lengths, areas and volumes keep the
+/// unit of length, so `(3 ft)^2` is in square feet and `cbrt(8 l)` is in
+/// decimeters.
+fn power_from_si(value: d128, dimensions: Dimensions, unit: Unit) -> Number {
+	let category = DIMENSIONAL_TYPES.iter().find(|t| t.dimensions() == Some(dimensions));
+	let power_unit = match (category, unit.category()) {
+		(Some(Area), Length) | (Some(Volume), Length) => {
+			POWER_UNITS.iter().find(|p| p.1 == unit && Some(&p.0.category()) == category).map(|p| p.0)
+		},
+		(Some(Length), Area) | (Some(Length), Volume) => {
+			POWER_UNITS.iter().find(|p| p.0 == unit).map(|p| p.1)
+		},
+		_ => None,
+	};
+	match power_unit {
+		Some(power_unit) => Number::new(value / power_unit.si_factor(), power_unit),
+		None => from_si(value, dimensions, unit, unit),
+	}
+}
+
+/// Returns the `degree`th root of a [`Number`] with a unit, for functions
+/// like `sqrt` and `cbrt`. The [`Dimensions`] of the unit are divided by
+/// `degree`, so the square root of [`Area`] is [`Length`], and the square
+/// root of [`Power`] times [`Resistance`] is [`Voltage`].
+///
+/// Returns [`Error::FractionalDimensions`] if the dimensions aren't divisible
+/// by `degree`, like the square root of [`Length`]. Odd roots of negative
+/// values are negative, but even roots of negative values need to be handled
+/// by the caller.
+pub fn root(number: Number, degree: i8) -> Result<Number, Error> {
+	let dimensions = number.unit.dimensions()
+		.ok_or(Error::UnexpectedUnit { unit: number.unit, span: None })?;
+	let dimensions = dimensions.root(degree).ok_or(Error::FractionalDimensions {
+		unit: number.unit,
+		exponent: d128!(1) / d128::from(degree as i32),
+		span: None,
+	})?;
+	let si_value = to_si(&number);
+	let value = evaluator::root(si_value.abs(), d128::from(degree as i32));
+	let value = if si_value.is_negative() { -value } else { value };
+	Ok(power_from_si(value, dimensions, number.unit))
+}
+
+/// If `exponent` is a fraction with a small denominator, like 0.5 or 1/3,
+/// returns its numerator and denominator
+fn as_fraction(exponent: d128) -> Option<(i8, i8)> {
+	for denominator in 1..=12 {
+		let numerator = exponent * d128::from(denominator);
+		let rounded = numerator.quantize(d128!(1));
+		// 1/3 * 3 is 0.99999..., so allow a tiny difference
+		if (numerator - rounded).abs() < d128!(0.000000000000000000000000000001) {
+			let numerator = i8::try_from(to_integer(rounded)?).ok()?;
+			return Some((numerator, denominator as i8));
+		}
+	}
+	None
+}
+
+/// Returns the value of a [`Number`] as an integer, or an error if it isn't one
+fn bitwise_integer(number: &Number) -> Result<i128, Error> {
+	to_integer(number.value).ok_or(Error::NotAnInteger { value: number.value, span: None })
+}
+
+fn from_integer(integer: i128) -> d128 {
+	d128::from_str(&integer.to_string()).unwrap()
+}
+
+/// Perform a bitwise [`BitAnd`](Operator::BitAnd), [`BitOr`](Operator::BitOr),
+/// [`BitXor`](Operator::BitXor), [`ShiftLeft`](Operator::ShiftLeft) or
+/// [`ShiftRight`](Operator::ShiftRight) on two [`Number`]s.
+///
+/// Values are treated as signed integers in two's complement, and need to be
+/// integers.
+///
+/// - [`NoType`] works with [`NoType`]
+/// - [`DigitalStorage`] can be shifted by [`NoType`], and the unit stays the same, so `1 KiB << 10` is `1024 KiB`
+/// - For `and`, `or` and `xor`, [`DigitalStorage`] also works with [`NoType`] and [`DigitalStorage`]
+pub fn bitwise(left: Number, right: Number, operator: Operator) -> Result<Number, Error> {
+	let lcat = left.unit.category();
+	let rcat = right.unit.category();
+	let is_shift = matches!(operator, Operator::ShiftLeft | Operator::ShiftRight);
+	let (left, right, unit) = match (lcat, rcat) {
+		(NoType, NoType) => (left, right, NoUnit),
+		(DigitalStorage, NoType) => {
+			let unit = left.unit;
+			(left, right, unit)
+		},
+		(NoType, DigitalStorage) if !is_shift => {
+			let unit = right.unit;
+			(left, right, unit)
+		},
+		(DigitalStorage, DigitalStorage) if !is_shift => {
+			let (left, right) = convert_to_lowest(left, right)?;
+			let unit = left.unit;
+			(left, right, unit)
+		},
+		_ => return Err(incompatible(left.unit, right.unit, operator)),
+	};
+	let l = bitwise_integer(&left)?;
+	let r = bitwise_integer(&right)?;
+	if is_shift && r < 0 {
+		return Err(Error::NegativeShift { value: right.value, span: None });
+	}
+	let result = match operator {
+		Operator::BitAnd => from_integer(l & r),
+		Operator::BitOr => from_integer(l | r),
+		Operator::BitXor => from_integer(l ^ r),
+		Operator::ShiftLeft => {
+			let shifted = if r < 127 { l.checked_mul(1 << r) } else { None };
+			match shifted {
+				Some(shifted) => from_integer(shifted),
+				// too large for i128, so fall back to multiplying
+				None => left.value * d128!(2).pow(right.value),
+			}
+		},
+		Operator::ShiftRight => from_integer(l >> r.min(127)),
+		_ => return Err(incompatible(left.unit, right.unit, operator)),
+	};
+	Ok(Number::new(result, unit))
+}
+
+/// Perform a bitwise [`BitNot`](Operator::BitNot) on a [`NoType`] or
+/// [`DigitalStorage`] [`Number`], so `not 5` is `-6`
+pub fn bitwise_not(number: Number) -> Result<Number, Error> {
+	match number.unit.category() {
+		NoType | DigitalStorage => {
+			let integer = bitwise_integer(&number)?;
+			Ok(Number::new(from_integer(!integer), number.unit))
+		},
+		_ => Err(Error::UnexpectedUnit { unit: number.unit, span: None }),
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// Units of the same [`UnitType`] in descending order, like [`Foot`] and
+/// [`Inch`], used to split a value into several units. See [`decompose`].
+pub struct MixedUnits {
+	/// Indices into [`Unit::ALL`], which keeps this small enough to be part
+	/// of a [`NumberFormat`](crate::NumberFormat)
+	indices: [u16; MixedUnits::MAX],
+	len: usize,
+}
+
+impl MixedUnits {
+	/// The maximum number of units a value can be split into
+	pub const MAX: usize = 4;
+
+	/// Returns [`Error::InvalidMixedUnits`] unless there are 2 to
+	/// [`MAX`](MixedUnits::MAX) units of the same type, from largest to
+	/// smallest, and the type allows mixed units
+	pub fn new(units: &[Unit]) -> Result<MixedUnits, Error> {
+		let first = units.first().ok_or(Error::InvalidMixedUnits { span: None })?;
+		let valid = (2..=MixedUnits::MAX).contains(&units.len())
+			&& first.category().allows_mixed_units()
+			&& units.windows(2).all(|pair| pair[0].category() == pair[1].category() && pair[0].weight() > pair[1].weight());
+		if !valid {
+			return Err(Error::InvalidMixedUnits { span: None });
+		}
+		let mut mixed = MixedUnits { indices: [0; MixedUnits::MAX], len: units.len() };
+		for (index, unit) in mixed.indices.iter_mut().zip(units) {
+			// Mixed units are never composite units or currencies, so they're all in Unit::ALL
+			*index = Unit::ALL.iter().position(|u| u == unit).ok_or(Error::InvalidMixedUnits { span: None })? as u16;
+		}
+		Ok(mixed)
+	}
+
+	/// The units, from largest to smallest
+	pub fn units(&self) -> Vec<Unit> {
+		self.indices[..self.len].iter().map(|index| Unit::ALL[*index as usize]).collect()
+	}
+}
+
+/// Split a [`Number`] into several units, like 67.3 inches into 5 feet and
+/// 7.3 inches. Every part except the last is an integer, and all parts have
+/// the sign of the number.
+///
+/// Returns [`Error::InvalidMixedUnits`] if the units aren't valid for
+/// [`MixedUnits`], and [`Error::CannotConvert`] if the number can't be
+/// converted to them.
+///
+/// Example:
+/// ```rust
+/// use cpc::units::{decompose, Unit};
+/// use cpc::Number;
+/// use decimal::d128;
+///
+/// let parts = decompose(Number::new(d128!(8000), Unit::Second), &[Unit::Hour, Unit::Minute, Unit::Second]).unwrap();
+/// let parts: Vec<String> = parts.iter().map(|part| part.to_string()).collect();
+/// assert_eq!(parts, ["2 Hour", "13 Minute", "20 Second"]);
+/// ```
+pub fn decompose(number: Number, units: &[Unit]) -> Result<Vec<Number>, Error> {
+	MixedUnits::new(units)?;
+	let (smallest, larger) = units.split_last().unwrap();
+	if number.unit.category() != smallest.category() {
+		return Err(Error::CannotConvert { from: number.unit, to: *smallest, span: None });
+	}
+	let value = convert(number, *smallest)?.value;
+	// how close a quotient needs to be to the next integer to be rounded up
+	// to it, so 1/3 h is 20 minutes, not 19.999… minutes
+	let rounding_tolerance = d128!(1E-25);
+	let mut remaining = value.abs();
+	let mut parts = Vec::with_capacity(units.len());
+	for unit in larger {
+		let factor = get_conversion_factor(*unit, *smallest);
+		let quotient = remaining / factor;
+		let mut part = floor(quotient);
+		if part + d128!(1) - quotient < rounding_tolerance {
+			part += d128!(1);
+		}
+		remaining -= part * factor;
+		if remaining.is_negative() {
+			remaining = d128!(0);
+		}
+		parts.push(Number::new(part, *unit));
+	}
+	// 7.0 becomes 7
+	parts.push(Number::new(remaining.reduce(), *smallest));
+	if value.is_negative() {
+		for part in &mut parts {
+			part.value = -part.value;
+		}
+	}
+	Ok(parts)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -818,9 +1724,7 @@ mod tests {
 
 			let result = convert(number, to_unit);
 			let string_result = &result.unwrap().value.to_string();
-			let float_result = f64::from_str(string_result).unwrap();
-
-			return float_result;
+			f64::from_str(string_result).unwrap()
 		}
 
 		assert_float_eq!(convert_test(1000.0, Nanosecond, Microsecond), 1.0);
@@ -850,6 +1754,18 @@ mod tests {
 		assert_float_eq!(convert_test(1852.0, Meter, NauticalMile), 1.0);
 		assert_float_eq!(convert_test(9460730472580800.0, Meter, LightYear), 1.0);
 		assert_float_eq!(convert_test(299792458.0, Meter, LightSecond), 1.0);
+		assert_float_eq!(convert_test(60.0, LightSecond, LightMinute), 1.0);
+		assert_float_eq!(convert_test(149597870.7, Kilometer, AstronomicalUnit), 1.0);
+		assert_float_eq!(convert_test(1.0, Parsec, AstronomicalUnit), 206264.80624709636);
+		assert_float_eq!(convert_test(1.0, EarthRadius, Kilometer), 6371.0088);
+		assert_float_eq!(convert_test(1.0, Nanometer, Picometer), 1000.0);
+		assert_float_eq!(convert_test(10.0, Angstrom, Nanometer), 1.0);
+		assert_float_eq!(convert_test(1000.0, Micrometer, Millimeter), 1.0);
+		assert_float_eq!(convert_test(1000.0, Thou, Inch), 1.0);
+		assert_float_eq!(convert_test(1.0, Fathom, Foot), 6.0);
+		assert_float_eq!(convert_test(4.0, Rod, Chain), 1.0);
+		assert_float_eq!(convert_test(10.0, Chain, Furlong), 1.0);
+		assert_float_eq!(convert_test(24.0, Furlong, League), 1.0);
 
 		assert_float_eq!(convert_test(100.0, SquareMillimeter, SquareCentimeter), 1.0);
 		assert_float_eq!(convert_test(100.0, SquareCentimeter, SquareDecimeter), 1.0);
@@ -997,6 +1913,39 @@ mod tests {
 		assert_float_eq!(convert_test(1000.0, Millivolt, Volt), 1.0);
 		assert_float_eq!(convert_test(1000.0, Volt, Kilovolt), 1.0);
 
+		assert_float_eq!(convert_test(1000.0, Microcoulomb, Millicoulomb), 1.0);
+		assert_float_eq!(convert_test(1000.0, Millicoulomb, Coulomb), 1.0);
+		assert_float_eq!(convert_test(3.6, Coulomb, MilliampereHour), 1.0);
+		assert_float_eq!(convert_test(1000.0, MilliampereHour, AmpereHour), 1.0);
+
+		assert_float_eq!(convert_test(1000.0, Picofarad, Nanofarad), 1.0);
+		assert_float_eq!(convert_test(1000.0, Nanofarad, Microfarad), 1.0);
+		assert_float_eq!(convert_test(1000.0, Microfarad, Millifarad), 1.0);
+		assert_float_eq!(convert_test(1000.0, Millifarad, Farad), 1.0);
+
+		assert_float_eq!(convert_test(1000.0, Nanohenry, Microhenry), 1.0);
+		assert_float_eq!(convert_test(1000.0, Microhenry, Millihenry), 1.0);
+		assert_float_eq!(convert_test(1000.0, Millihenry, Henry), 1.0);
+
+		assert_float_eq!(convert_test(1000.0, Microsiemens, Millisiemens), 1.0);
+		assert_float_eq!(convert_test(1000.0, Millisiemens, Siemens), 1.0);
+
+		assert_float_eq!(convert_test(100000.0, Maxwell, Milliweber), 1.0);
+		assert_float_eq!(convert_test(1000.0, Milliweber, Weber), 1.0);
+
+		assert_float_eq!(convert_test(1000.0, Nanotesla, Microtesla), 1.0);
+		assert_float_eq!(convert_test(1000.0, Microtesla, Millitesla), 1.0);
+		assert_float_eq!(convert_test(10.0, Gauss, Millitesla), 1.0);
+		assert_float_eq!(convert_test(10000.0, Gauss, Tesla), 1.0);
+
+		assert_float_eq!(convert_test(1000.0, Newton, Kilonewton), 1.0);
+		assert_float_eq!(convert_test(1000.0, Kilonewton, Meganewton), 1.0);
+		assert_float_eq!(convert_test(1.0, Newton, Dyne), 100000.0);
+		assert_float_eq!(convert_test(4.4482216152605, Newton, PoundForce), 1.0);
+		assert_float_eq!(convert_test(9.80665, Newton, KilogramForce), 1.0);
+		assert_float_eq!(convert_test(1000.0, PoundForce, Kip), 1.0);
+		assert_float_eq!(convert_test(0.138254954376, Newton, Poundal), 1.0);
+
 		assert_float_eq!(convert_test(1000.0, Pascal, Kilopascal), 1.0);
 		assert_float_eq!(convert_test(101325.0, Pascal, Atmosphere), 1.0);
 		assert_float_eq!(convert_test(100.0, Pascal, Millibar), 1.0);
@@ -1017,11 +1966,93 @@ mod tests {
 		assert_float_eq!(convert_test(1.609344, KilometersPerHour, MilesPerHour), 1.0);
 		assert_float_eq!(convert_test(1.852, KilometersPerHour, Knot), 1.0);
 
+		assert_float_eq!(convert_test(0.3048, MetersPerSecondSquared, FeetPerSecondSquared), 1.0);
+		assert_float_eq!(convert_test(9.80665, MetersPerSecondSquared, StandardGravity), 1.0);
+		assert_float_eq!(convert_test(1.0, MetersPerSecondSquared, Gal), 100.0);
+
+		assert_float_eq!(convert_test(60.0, LitersPerHour, LitersPerMinute), 1.0);
+		assert_float_eq!(convert_test(60.0, LitersPerMinute, LitersPerSecond), 1.0);
+		assert_float_eq!(convert_test(1000.0, LitersPerHour, CubicMetersPerHour), 1.0);
+		assert_float_eq!(convert_test(3600.0, CubicMetersPerHour, CubicMetersPerSecond), 1.0);
+		assert_float_eq!(convert_test(28.316846592, LitersPerMinute, CubicFeetPerMinute), 1.0);
+		assert_float_eq!(convert_test(60.0, CubicFeetPerMinute, CubicFeetPerSecond), 1.0);
+		assert_float_eq!(convert_test(3.785411784, LitersPerMinute, GallonsPerMinute), 1.0);
+		assert_float_eq!(convert_test(60.0, GallonsPerHour, GallonsPerMinute), 1.0);
+
+		assert_float_eq!(convert_test(1000.0, GramsPerSecond, KilogramsPerSecond), 1.0);
+		assert_float_eq!(convert_test(60.0, KilogramsPerHour, KilogramsPerMinute), 1.0);
+		assert_float_eq!(convert_test(60.0, KilogramsPerMinute, KilogramsPerSecond), 1.0);
+		assert_float_eq!(convert_test(1000.0, KilogramsPerHour, MetricTonsPerHour), 1.0);
+		assert_float_eq!(convert_test(0.45359237, KilogramsPerHour, PoundsPerHour), 1.0);
+		assert_float_eq!(convert_test(60.0, PoundsPerHour, PoundsPerMinute), 1.0);
+		assert_float_eq!(convert_test(60.0, PoundsPerMinute, PoundsPerSecond), 1.0);
+
+		assert_float_eq!(convert_test(1.0, GramsPerLiter, KilogramsPerCubicMeter), 1.0);
+		assert_float_eq!(convert_test(1000.0, KilogramsPerCubicMeter, KilogramsPerLiter), 1.0);
+		assert_float_eq!(convert_test(1.0, GramsPerCubicCentimeter, KilogramsPerLiter), 1.0);
+		assert_float_eq!(convert_test(1.0, GramsPerMilliliter, GramsPerCubicCentimeter), 1.0);
+		assert_float_eq!(convert_test(1000.0, KilogramsPerCubicMeter, PoundsPerCubicFoot), 62.42796057614461);
+		assert_float_eq!(convert_test(1728.0, PoundsPerCubicFoot, PoundsPerCubicInch), 1.0);
+		assert_float_eq!(convert_test(1.0, PoundsPerGallon, GramsPerLiter), 119.82642731689663);
+
+		assert_float_eq!(convert_test(1.609344, KilometersPerLiter, MilesPerGallon), 3.785411784);
+		assert_float_eq!(convert_test(1.609344, KilometersPerLiter, MilesPerImperialGallon), 4.54609);
+		assert_float_eq!(convert_test(5.0, LitersPer100Kilometers, KilometersPerLiter), 20.0);
+		assert_float_eq!(convert_test(20.0, KilometersPerLiter, LitersPer100Kilometers), 5.0);
+		assert_float_eq!(convert_test(30.0, MilesPerGallon, LitersPer100Kilometers), 7.840486111111111);
+		assert_float_eq!(convert_test(5.0, LitersPer100Kilometers, LitersPer100Kilometers), 5.0);
+		assert!(matches!(convert(Number::new(d128!(0), MilesPerGallon), LitersPer100Kilometers), Err(Error::InvalidFuelEconomy { .. })));
+		assert!(matches!(convert(Number::new(d128!(-5), LitersPer100Kilometers), KilometersPerLiter), Err(Error::InvalidFuelEconomy { .. })));
+		assert_float_eq!(convert_test(0.0, MilesPerGallon, KilometersPerLiter), 0.0);
+
 		assert_float_eq!(convert_test(274.15, Kelvin, Celsius), 1.0);
 		assert_float_eq!(convert_test(300.0, Kelvin, Fahrenheit), 80.33);
 		assert_float_eq!(convert_test(-272.15, Celsius, Kelvin), 1.0);
 		assert_float_eq!(convert_test(-15.0, Celsius, Fahrenheit), 5.0);
 		assert_float_eq!(convert_test(80.33, Fahrenheit, Kelvin), 300.0);
 		assert_float_eq!(convert_test(5.0, Fahrenheit, Celsius), -15.0);
+
+		assert_float_eq!(convert_test(60.0, Arcsecond, Arcminute), 1.0);
+		assert_float_eq!(convert_test(60.0, Arcminute, Degree), 1.0);
+		assert_float_eq!(convert_test(400.0, Gradian, Turn), 1.0);
+		assert_float_eq!(convert_test(360.0, Degree, Turn), 1.0);
+		assert_float_eq!(convert_test(1.0, Radian, Degree), 57.29577951308232);
+
+		let pressure = composite_unit(&[(Kilogram, 1), (Meter, -1), (Second, -2)]).unwrap();
+		assert_float_eq!(convert_test(1000.0, pressure, Kilopascal), 1.0);
+		assert_float_eq!(convert_test(1.0, Atmosphere, pressure), 101325.0);
+		let density = Composite(CompositeUnit::from_dimensions(Dimensions::MASS / Dimensions::LENGTH.pow(3)));
+		let grams_per_cm3 = composite_unit(&[(Gram, 1), (CubicCentimeter, -1)]).unwrap();
+		assert_float_eq!(convert_test(1000.0, density, grams_per_cm3), 1.0);
+		assert_float_eq!(convert_test(1.0, grams_per_cm3, GramsPerCubicCentimeter), 1.0);
+		assert_float_eq!(convert_test(1.0, Meter, composite_unit(&[(Meter, 2), (Meter, -1)]).unwrap()), 1.0);
+		let ampere2_ohm = composite_unit(&[(Ampere, 2), (Ohm, 1)]).unwrap();
+		assert_float_eq!(convert_test(1.0, ampere2_ohm, Watt), 1.0);
+		let volt2_per_ohm = composite_unit(&[(Volt, 2), (Ohm, -1)]).unwrap();
+		assert_float_eq!(convert_test(1.0, volt2_per_ohm, Milliwatt), 1000.0);
+		let ampere_ohm = composite_unit(&[(Ampere, 1), (Ohm, 1)]).unwrap();
+		assert_float_eq!(convert_test(1.0, ampere_ohm, Volt), 1.0);
+		let force = composite_unit(&[(Kilogram, 1), (Meter, 1), (Second, -2)]).unwrap();
+		assert_float_eq!(convert_test(9.80665, force, KilogramForce), 1.0);
+	}
+
+	#[test]
+	fn test_mixed_units() {
+		let decompose_test = |value: d128, unit: Unit, units: &[Unit]| -> Vec<String> {
+			decompose(Number::new(value, unit), units).unwrap().iter().map(Number::to_string).collect()
+		};
+		assert_eq!(decompose_test(d128!(67.3), Inch, &[Foot, Inch]), ["5 Foot", "7.3 Inch"]);
+		assert_eq!(decompose_test(d128!(8000), Second, &[Hour, Minute, Second]), ["2 Hour", "13 Minute", "20 Second"]);
+		assert_eq!(decompose_test(d128!(13.5), Stone, &[Stone, Pound]), ["13 Stone", "7 Pound"]);
+		assert_eq!(decompose_test(d128!(12.5125), Degree, &[Degree, Arcminute, Arcsecond]), ["12 Degree", "30 Arcminute", "45 Arcsecond"]);
+		assert_eq!(decompose_test(d128!(-67.3), Inch, &[Foot, Inch]), ["-5 Foot", "-7.3 Inch"]);
+		// 1/3 hour isn't exact, but shouldn't become 19 minutes 59.999... seconds
+		assert_eq!(decompose_test(d128!(1) / d128!(3), Hour, &[Hour, Minute, Second]), ["0 Hour", "20 Minute", "0 Second"]);
+
+		assert!(MixedUnits::new(&[Inch, Foot]).is_err());
+		assert!(MixedUnits::new(&[Foot]).is_err());
+		assert!(MixedUnits::new(&[Foot, Pound]).is_err());
+		assert!(MixedUnits::new(&[Celsius, Kelvin]).is_err());
+		assert!(decompose(Number::new(d128!(2), Kilogram), &[Foot, Inch]).is_err());
 	}
 }